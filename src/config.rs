@@ -0,0 +1,412 @@
+use crate::gql_queries::{AttributeInputTypeEnum, CountryCode, LanguageCodeEnum, OrderStatus};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const CONFIG_FILE: &str = "migration.yaml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Everything that differs between staging and production, read at runtime from a yaml file
+/// (see `migration.example.yaml`)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Config {
+    pub gql_endpoint: String,
+    pub sql_endpoint: String,
+    pub email: String,
+    pub password: String,
+    // All products are in this app assigned to a single warehouse and tax class and channel
+    /// Channel slug
+    pub channel: String,
+    /// Warehouse slug
+    pub warehouse: String,
+    /// Tax class name
+    pub tax_class: String,
+    /// If set, channel, warehouse and tax class that don't exist in saleor yet get created,
+    /// otherwise the run stops on them
+    #[serde(default)]
+    pub create_missing: Option<Bootstrap>,
+    /// Extra channels filled from the old `product_prices` table. Without any, every product is
+    /// only listed in `channel` with its `retail_price_with_iva`
+    #[serde(default)]
+    pub price_channels: Vec<PriceChannel>,
+    /// Folder the old shop's product and category pictures are in
+    pub media_root: PathBuf,
+    /// Re-encodes the pictures before they go up, see `images::prepare_images`. Without it the
+    /// originals from `media_root` are sent as they are
+    #[serde(default)]
+    pub image_preparation: Option<ImagePreparation>,
+    /// Where the media server the run starts over `media_dir()` listens
+    #[serde(default)]
+    pub media_server: MediaServerConfig,
+    /// How product pictures get to saleor
+    #[serde(default)]
+    pub media_upload: MediaUpload,
+    /// What the old `status` and `availability_text_id` of a product turn into
+    #[serde(default)]
+    pub availability: AvailabilityConfig,
+    /// Category → product type mapping made with `./category-tree-generator`
+    #[serde(default = "default_category_mapping")]
+    pub category_mapping: PathBuf,
+    /// Old categories that don't get migrated at all, matched by exact name
+    #[serde(default)]
+    pub excluded_categories: Vec<String>,
+    /// Rules for merging sibling products into one product with several variants. Without it
+    /// every old product becomes its own saleor product with a single variant
+    #[serde(default)]
+    pub variant_grouping: Option<VariantGrouping>,
+    /// Saleor attributes filled from old product columns. A product type gets the ones listed by
+    /// slug under `atributy` in the category mapping
+    #[serde(default)]
+    pub attributes: Vec<AttributeConfig>,
+    /// Products uploaded at the same time
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Upper limit on requests to saleor over all workers, unlimited if not set
+    #[serde(default)]
+    pub requests_per_second: Option<f64>,
+    /// Products per productBulkCreate request. If not set, every product goes through the single
+    /// product mutations
+    #[serde(default)]
+    pub bulk_size: Option<usize>,
+    /// Url patterns for the redirect map written after `migrate`, no map without them
+    #[serde(default)]
+    pub redirects: Option<RedirectConfig>,
+    /// The old shop's main language, its texts are what the products and categories get created
+    /// with
+    #[serde(default = "default_language_id")]
+    pub default_language_id: u32,
+    /// Old `language_id` → saleor language code (`EN`, `CS_CZ`...). Texts in these languages go up
+    /// as translations, other languages are left out
+    #[serde(default)]
+    pub languages: HashMap<u32, LanguageCodeEnum>,
+    /// Old shop accounts get migrated after the catalogue, no customers without it
+    #[serde(default)]
+    pub customers: Option<CustomersConfig>,
+    /// Old orders get imported as history after the customers, no orders without it
+    #[serde(default)]
+    pub orders: Option<OrdersConfig>,
+    /// Old product and category discounts become saleor sales, one per percentage, none without it
+    #[serde(default)]
+    pub discounts: Option<DiscountsConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CustomersConfig {
+    /// Old accounts that stay behind, e.g. the shop's own staff, matched case-insensitively
+    #[serde(default)]
+    pub excluded_emails: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrdersConfig {
+    /// The old shop only ever sold in one currency, e.g. `EUR`
+    pub currency: String,
+    /// What saleor sends the order emails in, e.g. `SK`
+    pub language_code: LanguageCodeEnum,
+    /// Old `status_id` → saleor status (`UNFULFILLED`, `FULFILLED`, `CANCELED`...)
+    #[serde(default)]
+    pub statuses: HashMap<u32, OrderStatus>,
+    /// For old statuses missing in `statuses`
+    #[serde(default = "default_order_status")]
+    pub default_status: OrderStatus,
+    /// Orders with lines whose product isn't in saleor
+    #[serde(default)]
+    pub missing_lines: MissingLines,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingLines {
+    /// Left out and reported, their totals wouldn't match the old ones
+    #[default]
+    Skip,
+    /// Imported without those lines, they're listed in the `missing_lines` metadata of the order
+    Metadata,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DiscountsConfig {
+    /// `{percentage}` gets replaced, e.g. `Zľava {percentage} %`
+    #[serde(default = "default_sale_name")]
+    pub sale_name: String,
+}
+
+/// `{id}` is the old id, `{slug}` the old slug in the old patterns and the saleor one in the new
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RedirectConfig {
+    pub old_category_url: String,
+    /// Old products have no slug column, theirs is made from the name like the saleor one
+    pub old_product_url: String,
+    #[serde(default = "default_new_category_url")]
+    pub new_category_url: String,
+    #[serde(default = "default_new_product_url")]
+    pub new_product_url: String,
+}
+
+/// What a fresh saleor instance needs to get the channel and warehouse created
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bootstrap {
+    pub channel_name: String,
+    pub currency_code: String,
+    pub default_country: CountryCode,
+    pub warehouse_name: String,
+    pub warehouse_address: AddressConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddressConfig {
+    #[serde(default)]
+    pub company_name: Option<String>,
+    pub street_address_1: String,
+    pub city: String,
+    pub postal_code: String,
+    pub country: CountryCode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImagePreparation {
+    /// Folder the prepared pictures are written to, named by content hash
+    #[serde(default = "default_prepared_media")]
+    pub output: PathBuf,
+    /// Pictures with a longer side than this (in pixels) get scaled down
+    #[serde(default = "default_max_dimension")]
+    pub max_dimension: u32,
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaUpload {
+    /// Saleor downloads them from the media server, it has to reach this machine for that
+    #[default]
+    Url,
+    /// Every picture is sent along with its `productMediaCreate` as a multipart request, for
+    /// saleors that can't reach us (cloud). Slower, there's no media server and no bulk media
+    Multipart,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct AvailabilityConfig {
+    /// How products whose whole family is `ended` are listed
+    #[serde(default)]
+    pub ended: EndedProducts,
+    /// Old `availability_text_id` → text ("Skladom", "Do 2 týždňov"...), it goes into the
+    /// `availability` metadata of the product and its variants. Unmapped ids are left out
+    #[serde(default)]
+    pub texts: HashMap<i64, String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EndedProducts {
+    /// Published so the old links still work, but not in listings and not for sale
+    #[default]
+    Hidden,
+    /// Not published at all
+    Unpublished,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MediaServerConfig {
+    #[serde(default = "default_media_bind")]
+    pub bind: SocketAddr,
+    /// Base url saleor downloads the pictures from, `http://{local ip}:{bind port}` if not set.
+    /// Needed when saleor can't reach this machine directly, e.g. through docker or a tunnel
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+impl Default for MediaServerConfig {
+    fn default() -> Self {
+        MediaServerConfig {
+            bind: default_media_bind(),
+            public_url: None,
+        }
+    }
+}
+
+/// Maps one old store + country pair from `product_prices` to a saleor channel
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PriceChannel {
+    /// Channel slug, has to exist in saleor already
+    pub channel: String,
+    pub store_id: u32,
+    pub country_id: String,
+    /// Send `price` instead of `price_with_vat`
+    #[serde(default)]
+    pub without_vat: bool,
+    /// Send the wholesale price as the variant's cost price
+    #[serde(default)]
+    pub wholesale_as_cost_price: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VariantGrouping {
+    /// Dropdown attribute the variants differ in, gets created if the slug doesn't exist yet
+    pub attribute_name: String,
+    pub attribute_slug: String,
+    /// Tried in order, the first rule that matches a product decides its family
+    pub rules: Vec<GroupingRule>,
+}
+
+/// Regex with a `base` group shared by the whole family and a `variant` group that becomes the
+/// variant's name and attribute value
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupingRule {
+    pub by: GroupBy,
+    pub pattern: Pattern,
+}
+
+/// Regex compiled while the config loads, so a broken one stops the run before anything starts
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern(pub regex::Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        regex::Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(|e| format!("invalid pattern '{}': {}", pattern, e))
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> Self {
+        pattern.0.as_str().to_owned()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// The old `code`
+    Sku,
+    Name,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttributeConfig {
+    pub name: String,
+    pub slug: String,
+    /// `DROPDOWN`, `NUMERIC`, `PLAIN_TEXT`, `BOOLEAN`...
+    pub input_type: AttributeInputTypeEnum,
+    pub source: AttributeSource,
+    /// Old id → text, for sources that are ids into other old tables
+    #[serde(default)]
+    pub values: HashMap<i64, String>,
+}
+
+/// Column of the old `products` table an attribute value comes from
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeSource {
+    Weight,
+    Quantity,
+    Unit,
+    AvailabilityText,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_media_bind() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], 38008))
+}
+
+fn default_prepared_media() -> PathBuf {
+    PathBuf::from("./media/prepared")
+}
+
+fn default_max_dimension() -> u32 {
+    2048
+}
+
+fn default_jpeg_quality() -> u8 {
+    88
+}
+
+fn default_new_category_url() -> String {
+    "/categories/{slug}/".to_owned()
+}
+
+fn default_new_product_url() -> String {
+    "/products/{slug}/".to_owned()
+}
+
+fn default_language_id() -> u32 {
+    1
+}
+
+fn default_sale_name() -> String {
+    "Old shop discount {percentage}%".to_owned()
+}
+
+/// Old orders are mostly long shipped
+fn default_order_status() -> OrderStatus {
+    OrderStatus::Fulfilled
+}
+
+fn default_category_mapping() -> PathBuf {
+    PathBuf::from("./filled_out_kategorie.yaml")
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read config file '{}'", path.display()))?;
+        let config: Self = serde_yaml::from_str(&data)
+            .with_context(|| format!("Invalid config file '{}'", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config file '{}'", path.display()))?;
+        Ok(config)
+    }
+
+    /// Values serde takes but the migration can't run with
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.concurrency == 0 {
+            anyhow::bail!("concurrency has to be at least 1");
+        }
+        if let Some(rps) = self.requests_per_second {
+            // Also refuses NaN, infinity and anything so small the interval wouldn't fit
+            if rps.is_nan()
+                || rps <= 0.0
+                || std::time::Duration::try_from_secs_f64(1.0 / rps).is_err()
+            {
+                anyhow::bail!(
+                    "requests_per_second has to be a positive number, got {}",
+                    rps
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Folder the pictures that go to saleor are in, the prepared ones if there are any
+    pub fn media_dir(&self) -> &Path {
+        match &self.image_preparation {
+            Some(preparation) => &preparation.output,
+            None => &self.media_root,
+        }
+    }
+}
+
+/// Loads the config for the rest of the run, has to be called once before `config()`
+pub fn init(path: &Path) -> anyhow::Result<()> {
+    let config = Config::load(path)?;
+    CONFIG
+        .set(config)
+        .map_err(|_| anyhow::anyhow!("config was already loaded"))
+}
+
+pub fn config() -> &'static Config {
+    CONFIG.get().expect("config::init wasn't called")
+}
@@ -0,0 +1,343 @@
+use crate::config::config;
+use crate::get_sqls::{FinalCustomer, UserAddress};
+use crate::gql_queries::{
+    customer_bulk_update, customer_create, customer_input, non_empty, AccountErrorCode,
+    CountryCode, CustomerBulkUpdateInput,
+};
+use crate::report::{Outcome, Report};
+use crate::saleor::{MutationError, Saleor, SaleorError};
+use crate::state::{MigrationState, OldKey, Step};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+
+/// Customers per customerBulkUpdate when there's no `bulk_size`
+const BULK_UPDATE_SIZE: usize = 50;
+
+/// Fields saleor's address validation complains about
+const ADDRESS_FIELDS: &[&str] = &[
+    "defaultBillingAddress",
+    "defaultShippingAddress",
+    "streetAddress1",
+    "streetAddress2",
+    "city",
+    "cityArea",
+    "postalCode",
+    "country",
+    "countryArea",
+    "companyName",
+    "phone",
+];
+
+/// Creates the old accounts that aren't in saleor yet one by one with customerCreate, the ones
+/// already there (`existing`, by old_id) get customerBulkUpdate'd. Nobody gets a password, they
+/// set one through the password reset
+pub async fn migrate_customers(
+    customers: &[FinalCustomer],
+    existing: &HashMap<u32, cynic::Id>,
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+) {
+    let mut creates = vec![];
+    let mut updates = vec![];
+    for customer in eligible(customers, report) {
+        let key = OldKey::Customer(customer.user.id);
+        if state.lock().is_done(&key, Step::Create) {
+            continue;
+        }
+        match existing.get(&customer.user.id) {
+            Some(saleor_id) => updates.push((customer, saleor_id)),
+            None => creates.push(customer),
+        }
+    }
+    println!(
+        "Creating {} customers, updating {}...",
+        creates.len(),
+        updates.len()
+    );
+    futures::stream::iter(creates)
+        .for_each_concurrent(config().concurrency, |customer| async move {
+            if let Err(e) = create_customer(customer, state, report, saleor).await {
+                report.record(Outcome::error(
+                    "CustomerCreate",
+                    customer.user.id,
+                    &customer.email,
+                    format!("{:?}", e),
+                ));
+            }
+        })
+        .await;
+    let bulk_size = config().bulk_size.unwrap_or(BULK_UPDATE_SIZE).max(1);
+    for batch in updates.chunks(bulk_size) {
+        if let Err(e) = update_batch(batch, state, report, saleor).await {
+            for (customer, _) in batch {
+                report.record(Outcome::error(
+                    "CustomerBulkUpdate",
+                    customer.user.id,
+                    &customer.email,
+                    format!("{:?}", e),
+                ));
+            }
+        }
+    }
+}
+
+/// Leaves out excluded accounts, ones without a usable email and ones whose email an earlier
+/// account already has, saleor wants them unique
+pub fn eligible<'a>(customers: &'a [FinalCustomer], report: &Report) -> Vec<&'a FinalCustomer> {
+    let excluded: HashSet<String> = config()
+        .customers
+        .iter()
+        .flat_map(|c| &c.excluded_emails)
+        .map(|email| email.trim().to_lowercase())
+        .collect();
+    let mut seen = HashSet::new();
+    customers
+        .iter()
+        .filter(|customer| {
+            let reason = if excluded.contains(&customer.email) {
+                "excluded in the config"
+            } else if !valid_email(&customer.email) {
+                "no usable email"
+            } else if !seen.insert(customer.email.clone()) {
+                "email already taken by another old account"
+            } else {
+                return true;
+            };
+            println!(
+                "customer '{}: {}' left out, {}",
+                customer.user.id, customer.email, reason
+            );
+            report.skipped(customer.user.id, &customer.email, reason);
+            false
+        })
+        .collect()
+}
+
+/// Newest address that has everything saleor needs. The broken ones before it go into the report
+pub fn default_address<'a>(
+    customer: &'a FinalCustomer,
+    report: &Report,
+) -> Option<(&'a UserAddress, CountryCode)> {
+    for address in &customer.addresses {
+        match validate_address(address) {
+            Ok(country) => return Some((address, country)),
+            Err(reason) => {
+                report.invalid_address(customer.user.id, &customer.email, address.id, reason)
+            }
+        }
+    }
+    None
+}
+
+async fn create_customer(
+    customer: &FinalCustomer,
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+) -> anyhow::Result<()> {
+    let old_id = customer.user.id.to_string();
+    let address = default_address(customer, report);
+    let input = customer_input(customer, &old_id, address);
+    // Saleor checks postal codes and phones per country, what it refuses the customer goes without
+    let result = match (customer_create(input, saleor).await, address) {
+        (Err(e), Some((address, _))) if is_address_error(&e) => {
+            report.invalid_address(
+                customer.user.id,
+                &customer.email,
+                address.id,
+                &e.to_string(),
+            );
+            customer_create(customer_input(customer, &old_id, None), saleor).await
+        }
+        (result, _) => result,
+    };
+    match result {
+        Ok(saleor_id) => {
+            let key = OldKey::Customer(customer.user.id);
+            state.lock().record(key, Step::Create, vec![saleor_id])?;
+            report.record(Outcome::ok(
+                "CustomerCreate",
+                customer.user.id,
+                &customer.email,
+            ));
+        }
+        Err(e) => report.record(Outcome::saleor_error(
+            "CustomerCreate",
+            customer.user.id,
+            &customer.email,
+            &e,
+        )),
+    }
+    Ok(())
+}
+
+async fn update_batch(
+    batch: &[(&FinalCustomer, &cynic::Id)],
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+) -> anyhow::Result<()> {
+    let old_ids: Vec<String> = batch
+        .iter()
+        .map(|(customer, _)| customer.user.id.to_string())
+        .collect();
+    let inputs = batch
+        .iter()
+        .zip(&old_ids)
+        .map(|((customer, saleor_id), old_id)| CustomerBulkUpdateInput {
+            id: Some(*saleor_id),
+            input: customer_input(customer, old_id, default_address(customer, report)),
+        })
+        .collect();
+    let results = match customer_bulk_update(inputs, saleor).await {
+        Ok(results) => results,
+        Err(e) => {
+            for (customer, _) in batch {
+                report.record(Outcome::saleor_error(
+                    "CustomerBulkUpdate",
+                    customer.user.id,
+                    &customer.email,
+                    &e,
+                ));
+            }
+            return Ok(());
+        }
+    };
+    for ((customer, saleor_id), result) in batch.iter().zip(results) {
+        let errors = result.errors.unwrap_or_default();
+        if result.customer.is_some() && errors.is_empty() {
+            let key = OldKey::Customer(customer.user.id);
+            state
+                .lock()
+                .record(key, Step::Create, vec![(*saleor_id).clone()])?;
+            report.record(Outcome::ok(
+                "CustomerBulkUpdate",
+                customer.user.id,
+                &customer.email,
+            ));
+            continue;
+        }
+        for error in errors.iter().filter(|e| {
+            e.path
+                .as_deref()
+                .is_some_and(|path| path.contains("Address"))
+        }) {
+            let address_id = customer
+                .addresses
+                .first()
+                .map(|a| a.id.to_string())
+                .unwrap_or_default();
+            report.invalid_address(
+                customer.user.id,
+                &customer.email,
+                address_id,
+                error.message.as_deref().unwrap_or_default(),
+            );
+        }
+        let errors: Vec<MutationError<_>> = errors.iter().map(|e| e.to_mutation_error()).collect();
+        report.record(Outcome::saleor_error(
+            "CustomerBulkUpdate",
+            customer.user.id,
+            &customer.email,
+            &SaleorError::Mutation(errors),
+        ));
+    }
+    Ok(())
+}
+
+/// Country of an address that has everything saleor needs, why not otherwise
+pub fn validate_address(address: &UserAddress) -> Result<CountryCode, &'static str> {
+    if non_empty(&address.street).is_none() {
+        return Err("no street");
+    }
+    if non_empty(&address.city).is_none() {
+        return Err("no city");
+    }
+    if non_empty(&address.zip).is_none() {
+        return Err("no postal code");
+    }
+    let country = non_empty(&address.country_id).ok_or("no country")?;
+    serde_json::from_value(serde_json::Value::String(country.to_uppercase()))
+        .map_err(|_| "unknown country code")
+}
+
+fn valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && domain.contains('.')
+                && !domain.contains('@')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn is_address_error(error: &SaleorError<AccountErrorCode>) -> bool {
+    matches!(error, SaleorError::Mutation(errors) if errors.iter().any(|e| {
+        e.field
+            .as_deref()
+            .is_some_and(|field| ADDRESS_FIELDS.contains(&field))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(street: &str, city: &str, zip: &str, country: &str) -> UserAddress {
+        let field = |text: &str| Some(text.to_owned());
+        UserAddress {
+            id: 1,
+            user_id: 1,
+            first_name: field("Jana"),
+            last_name: field("Nová"),
+            company: None,
+            street: field(street),
+            city: field(city),
+            zip: field(zip),
+            country_id: field(country),
+            phone: None,
+        }
+    }
+
+    #[test]
+    fn complete_address_has_a_country() {
+        let address = address("Hlavná 1", "Košice", "040 01", "sk");
+        assert_eq!(validate_address(&address), Ok(CountryCode::Sk));
+    }
+
+    #[test]
+    fn incomplete_address_says_why() {
+        let cases = [
+            (address(" ", "Košice", "040 01", "SK"), "no street"),
+            (address("Hlavná 1", "", "040 01", "SK"), "no city"),
+            (address("Hlavná 1", "Košice", "", "SK"), "no postal code"),
+            (address("Hlavná 1", "Košice", "040 01", ""), "no country"),
+            (
+                address("Hlavná 1", "Košice", "040 01", "XX"),
+                "unknown country code",
+            ),
+        ];
+        for (address, why) in cases {
+            assert_eq!(validate_address(&address), Err(why));
+        }
+        let mut address = address("Hlavná 1", "Košice", "040 01", "SK");
+        address.city = None;
+        assert_eq!(validate_address(&address), Err("no city"));
+    }
+
+    #[test]
+    fn emails() {
+        assert!(valid_email("jana@example.sk"));
+        assert!(valid_email("jana.nova+shop@mail.example.sk"));
+        assert!(!valid_email(""));
+        assert!(!valid_email("jana"));
+        assert!(!valid_email("@example.sk"));
+        assert!(!valid_email("jana@localhost"));
+        assert!(!valid_email("jana@a@example.sk"));
+        assert!(!valid_email("jana nova@example.sk"));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::config::config;
+use crate::get_sqls::{FinalCategory, FinalProduct};
+use crate::gql_queries::{
+    sale_catalogues_add, sale_channel_listing_update, sale_create, CatalogueInput,
+};
+use crate::report::{Outcome, Report};
+use crate::saleor::Saleor;
+use crate::state::{MigrationState, OldKey, Step};
+use parking_lot::{Mutex, RwLock};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Products, variants or categories per saleCataloguesAdd
+pub const CATALOGUE_CHUNK: usize = 100;
+
+/// Everything the old shop discounted by the same percentage, it all goes into one sale
+pub struct Discount<'a> {
+    pub percentage: Decimal,
+    /// Products whose whole family had this discount
+    pub products: Vec<&'a FinalProduct>,
+    /// Family members discounted on their own, they get it as variants
+    pub variants: Vec<&'a FinalProduct>,
+    pub categories: Vec<Arc<RwLock<FinalCategory>>>,
+}
+
+impl Discount<'_> {
+    /// Old id of the sale in the state and report
+    pub fn key(&self) -> String {
+        self.percentage.to_string()
+    }
+
+    pub fn name(&self) -> String {
+        let settings = config()
+            .discounts
+            .as_ref()
+            .expect("discounts aren't configured");
+        settings
+            .sale_name
+            .replace("{percentage}", &self.percentage.to_string())
+    }
+}
+
+/// Old discounts grouped by percentage, smallest first
+pub fn group<'a>(
+    categories: &[Arc<RwLock<FinalCategory>>],
+    products: &'a [FinalProduct],
+) -> Vec<Discount<'a>> {
+    let mut discounts = BTreeMap::new();
+    for product in products {
+        let family: Vec<&FinalProduct> =
+            std::iter::once(product).chain(&product.siblings).collect();
+        let first = percentage(product.product.discount.into());
+        if family
+            .iter()
+            .all(|member| percentage(member.product.discount.into()) == first)
+        {
+            if let Some(first) = first {
+                entry(&mut discounts, first).products.push(product);
+            }
+            continue;
+        }
+        for member in family {
+            if let Some(value) = percentage(member.product.discount.into()) {
+                entry(&mut discounts, value).variants.push(member);
+            }
+        }
+    }
+    for category in categories {
+        let discount = category.read().category.read().discount;
+        if let Some(value) = discount.and_then(percentage) {
+            entry(&mut discounts, value)
+                .categories
+                .push(category.clone());
+        }
+    }
+    discounts.into_values().collect()
+}
+
+fn entry<'m, 'a>(
+    discounts: &'m mut BTreeMap<Decimal, Discount<'a>>,
+    percentage: Decimal,
+) -> &'m mut Discount<'a> {
+    discounts.entry(percentage).or_insert_with(|| Discount {
+        percentage,
+        products: vec![],
+        variants: vec![],
+        categories: vec![],
+    })
+}
+
+/// 0 is no discount, anything over 100 is junk
+fn percentage(discount: f64) -> Option<Decimal> {
+    Decimal::from_f64(discount)
+        .map(|d| d.round_dp(2).normalize())
+        .filter(|d| *d > Decimal::ZERO && *d <= Decimal::ONE_HUNDRED)
+}
+
+/// saleCataloguesAdd inputs for the given saleor ids, at most `CATALOGUE_CHUNK` ids each
+pub fn catalogue_inputs<'a>(
+    products: &'a [cynic::Id],
+    variants: &'a [cynic::Id],
+    categories: &'a [cynic::Id],
+) -> Vec<CatalogueInput<'a>> {
+    let chunks = |ids: &'a [cynic::Id]| {
+        ids.chunks(CATALOGUE_CHUNK)
+            .map(|chunk| Some(chunk.iter().collect::<Vec<_>>()))
+    };
+    let mut inputs = vec![];
+    inputs.extend(chunks(products).map(|products| CatalogueInput {
+        products,
+        variants: None,
+        categories: None,
+    }));
+    inputs.extend(chunks(variants).map(|variants| CatalogueInput {
+        products: None,
+        variants,
+        categories: None,
+    }));
+    inputs.extend(chunks(categories).map(|categories| CatalogueInput {
+        products: None,
+        variants: None,
+        categories,
+    }));
+    inputs
+}
+
+/// A sale per percentage, listed in every channel, with the products, variants and categories
+/// that made it to saleor. A sale `existing` already has by name is taken over instead of created
+pub async fn migrate_discounts(
+    discounts: &[Discount<'_>],
+    existing: &HashMap<String, cynic::Id>,
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+    channel_ids: &HashMap<String, cynic::Id>,
+) {
+    println!("Creating {} sales...", discounts.len());
+    for discount in discounts {
+        if let Err(e) =
+            migrate_discount(discount, existing, state, report, saleor, channel_ids).await
+        {
+            report.record(Outcome::error(
+                "SaleCreate",
+                discount.key(),
+                &discount.name(),
+                format!("{:?}", e),
+            ));
+        }
+    }
+}
+
+async fn migrate_discount(
+    discount: &Discount<'_>,
+    existing: &HashMap<String, cynic::Id>,
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+    channel_ids: &HashMap<String, cynic::Id>,
+) -> anyhow::Result<()> {
+    let key = OldKey::Sale(discount.key());
+    let name = discount.name();
+    let created = state.lock().id(&key, Step::Create);
+    let sale_id = match (created, existing.get(&name)) {
+        (Some(id), _) => id,
+        (None, Some(id)) => {
+            state
+                .lock()
+                .record(key.clone(), Step::Create, vec![id.clone()])?;
+            id.clone()
+        }
+        (None, None) => match sale_create(&name, saleor).await {
+            Ok(id) => {
+                state
+                    .lock()
+                    .record(key.clone(), Step::Create, vec![id.clone()])?;
+                report.record(Outcome::ok("SaleCreate", discount.key(), &name));
+                id
+            }
+            Err(e) => {
+                report.record(Outcome::saleor_error(
+                    "SaleCreate",
+                    discount.key(),
+                    &name,
+                    &e,
+                ));
+                return Ok(());
+            }
+        },
+    };
+
+    let listed = state.lock().is_done(&key, Step::ChannelListing);
+    if !listed {
+        let channels = channel_ids.values().collect();
+        match sale_channel_listing_update(&sale_id, channels, discount.percentage, saleor).await {
+            Ok(()) => {
+                state
+                    .lock()
+                    .record(key.clone(), Step::ChannelListing, vec![])?;
+                report.record(Outcome::ok(
+                    "SaleChannelListingUpdate",
+                    discount.key(),
+                    &name,
+                ));
+            }
+            Err(e) => {
+                report.record(Outcome::saleor_error(
+                    "SaleChannelListingUpdate",
+                    discount.key(),
+                    &name,
+                    &e,
+                ));
+                return Ok(());
+            }
+        }
+    }
+
+    if state.lock().is_done(&key, Step::Catalogue) {
+        return Ok(());
+    }
+    // Members that aren't in saleor (yet) are left out and keep the catalogue step open
+    let mut missing: Vec<String> = vec![];
+    let mut products: Vec<cynic::Id> = vec![];
+    for product in &discount.products {
+        match &product.saleor_id {
+            Some(id) => products.push(id.clone()),
+            None => missing.push(format!("product {}", product.product.id)),
+        }
+    }
+    let mut variants: Vec<cynic::Id> = vec![];
+    for variant in &discount.variants {
+        let id = state
+            .lock()
+            .id(&OldKey::Product(variant.product.id), Step::Variant);
+        match id {
+            Some(id) => variants.push(id),
+            None => missing.push(format!("variant {}", variant.product.id)),
+        }
+    }
+    let mut categories: Vec<cynic::Id> = vec![];
+    for category in &discount.categories {
+        let category = category.read();
+        match &category.saleor_id {
+            Some(id) => categories.push(id.clone()),
+            None => missing.push(format!("category {}", category.category.read().id)),
+        }
+    }
+    // Adding what's already in the sale is fine, so a failed chunk just reruns with --resume
+    let mut failed = false;
+    for catalogue in catalogue_inputs(&products, &variants, &categories) {
+        if let Err(e) = sale_catalogues_add(&sale_id, catalogue, saleor).await {
+            report.record(Outcome::saleor_error(
+                "SaleCataloguesAdd",
+                discount.key(),
+                &name,
+                &e,
+            ));
+            failed = true;
+        }
+    }
+    if !missing.is_empty() {
+        report.record(Outcome::error(
+            "SaleCataloguesAdd",
+            discount.key(),
+            &name,
+            format!("not in saleor, left out: {}", missing.join(", ")),
+        ));
+    } else if !failed {
+        state.lock().record(key, Step::Catalogue, vec![])?;
+        report.record(Outcome::ok("SaleCataloguesAdd", discount.key(), &name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn percentages() {
+        let decimal = |text| Some(Decimal::from_str(text).unwrap());
+        assert_eq!(percentage(10.0), decimal("10"));
+        assert_eq!(percentage(12.5), decimal("12.5"));
+        assert_eq!(percentage(33.333), decimal("33.33"));
+        assert_eq!(percentage(100.0), decimal("100"));
+        assert_eq!(percentage(0.001), None);
+        assert_eq!(percentage(0.0), None);
+        assert_eq!(percentage(-5.0), None);
+        assert_eq!(percentage(100.5), None);
+        assert_eq!(percentage(f64::NAN), None);
+    }
+}
@@ -0,0 +1,553 @@
+use crate::config::{config, MediaUpload};
+use crate::customers;
+use crate::discounts;
+use crate::get_sqls::{FinalCustomer, FinalOrder, FinalProduct, FinalProductType, QueryAllResult};
+use crate::gql_queries::{
+    assign_attributes_operation, attribute_create_operation, category_input,
+    category_translate_operation, customer_create_operation, customer_input, file_upload_operation,
+    listed_channels, order_bulk_create_operation, product_bulk_create_input,
+    product_bulk_create_operation, product_channel_listing_update_operation,
+    product_create_operation, product_media_create_operation, product_translate_operation,
+    product_type_create_operation, sale_catalogues_add_operation,
+    sale_channel_listing_update_operation, sale_create_operation, update_metadata_operation,
+    variant_create_operation, variant_listing_update_operation, variant_listings, AttributeIds,
+    AttributeInputTypeEnum, CreateCategory, CreateCategoryVariables, MetadataInput,
+    ORDER_BULK_LIMIT,
+};
+use crate::orders;
+use crate::report::Report;
+use cynic::MutationBuilder;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::prelude::*;
+
+pub const DRY_RUN_FILE: &str = "dry_run.ndjson";
+
+/// One line of the dry run file
+#[derive(Serialize)]
+struct PlannedOperation<'a, T: Serialize> {
+    step: &'a str,
+    old_id: String,
+    /// File that would be sent as multipart upload next to the operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upload: Option<String>,
+    operation: T,
+}
+
+/// Saleor ids don't exist yet, so everything that references an object created earlier in the
+/// run gets a readable stand-in instead
+fn placeholder(kind: &str, old_id: impl std::fmt::Display) -> cynic::Id {
+    cynic::Id::new(format!("dry-run:{kind}:{old_id}"))
+}
+
+struct DryRun {
+    file: std::fs::File,
+    count: usize,
+}
+
+impl DryRun {
+    fn write<T: Serialize>(
+        &mut self,
+        step: &str,
+        old_id: impl ToString,
+        upload: Option<String>,
+        operation: T,
+    ) -> anyhow::Result<()> {
+        let planned = PlannedOperation {
+            step,
+            old_id: old_id.to_string(),
+            upload,
+            operation,
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&planned)?)?;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// fileUpload for every attachment of the family, then the metadata that lists them
+fn write_attachments(
+    out: &mut DryRun,
+    product: &FinalProduct,
+    product_id: &cynic::Id,
+) -> anyhow::Result<()> {
+    let mut listed = vec![];
+    for variant in std::iter::once(product).chain(product.siblings.iter()) {
+        for (file_name, _) in &variant.attachments {
+            if listed.iter().any(|(name, _)| name == file_name) {
+                continue;
+            }
+            out.write(
+                "FileUpload",
+                variant.product.id,
+                Some(file_name.clone()),
+                file_upload_operation(),
+            )?;
+            listed.push((
+                file_name.clone(),
+                format!("dry-run:attachment:{}", file_name),
+            ));
+        }
+    }
+    if listed.is_empty() {
+        return Ok(());
+    }
+    let listed: Vec<_> = listed
+        .iter()
+        .map(|(name, url)| serde_json::json!({ "name": name, "url": url }))
+        .collect();
+    let value = serde_json::to_string(&listed)?;
+    out.write(
+        "UpdateMetadata",
+        product.product.id,
+        None,
+        update_metadata_operation(
+            product_id,
+            vec![MetadataInput {
+                key: "attachments",
+                value: &value,
+            }],
+        ),
+    )
+}
+
+/// productTranslate for every configured language the product has texts in
+fn write_translations(
+    out: &mut DryRun,
+    product: &FinalProduct,
+    product_id: &cynic::Id,
+) -> anyhow::Result<()> {
+    for translation in &product.translations {
+        out.write(
+            "ProductTranslate",
+            product.product.id,
+            None,
+            product_translate_operation(product_id, translation),
+        )?;
+    }
+    Ok(())
+}
+
+/// customerCreate for every account the real run would create, as if none were in saleor yet
+fn write_customers(out: &mut DryRun, customers: &[FinalCustomer]) -> anyhow::Result<()> {
+    // the real run reports what's left out, here it's only printed
+    let report = Report::default();
+    for customer in customers::eligible(customers, &report) {
+        let old_id = customer.user.id.to_string();
+        let address = customers::default_address(customer, &report);
+        out.write(
+            "CustomerCreate",
+            &old_id,
+            None,
+            customer_create_operation(customer_input(customer, &old_id, address)),
+        )?;
+    }
+    Ok(())
+}
+
+/// orderBulkCreate for every order the real run would import, as if every product and account
+/// had made it to saleor
+fn write_orders(
+    out: &mut DryRun,
+    orders: &[FinalOrder],
+    products: &[FinalProduct],
+    customers: &[FinalCustomer],
+    warehouse_id: &cynic::Id,
+) -> anyhow::Result<()> {
+    let Some(settings) = &config().orders else {
+        return Ok(());
+    };
+    let variants: orders::Variants = products
+        .iter()
+        .flat_map(|p| std::iter::once(p).chain(&p.siblings))
+        .map(|variant| {
+            let id = placeholder("variant", variant.product.id);
+            (variant.product.id, id)
+        })
+        .collect();
+    let customer_ids: HashSet<u32> = customers.iter().map(|c| c.user.id).collect();
+    let report = Report::default();
+    let prepared: Vec<orders::PreparedOrder> = orders
+        .iter()
+        .filter_map(|order| {
+            let mut prepared = orders::prepare(order, &variants, settings, &report)?;
+            prepared.customer_id = order
+                .order
+                .user_id
+                .filter(|user_id| customer_ids.contains(user_id))
+                .map(|user_id| placeholder("customer", user_id));
+            Some(prepared)
+        })
+        .collect();
+    let bulk_size = config()
+        .bulk_size
+        .unwrap_or(ORDER_BULK_LIMIT)
+        .clamp(1, ORDER_BULK_LIMIT);
+    for batch in prepared.chunks(bulk_size) {
+        let old_ids: Vec<&str> = batch.iter().map(|order| order.old_id.as_str()).collect();
+        out.write(
+            "OrderBulkCreate",
+            old_ids.join(","),
+            None,
+            order_bulk_create_operation(orders::inputs(batch, settings, warehouse_id)),
+        )?;
+    }
+    Ok(())
+}
+
+/// saleCreate, its channel listing and catalogue for every discount percentage
+fn write_discounts(
+    out: &mut DryRun,
+    data: &QueryAllResult,
+    channel_ids: &HashMap<String, cynic::Id>,
+) -> anyhow::Result<()> {
+    for discount in discounts::group(&data.categories, &data.products) {
+        let name = discount.name();
+        let sale_id = placeholder("sale", discount.key());
+        out.write(
+            "SaleCreate",
+            discount.key(),
+            None,
+            sale_create_operation(&name),
+        )?;
+        out.write(
+            "SaleChannelListingUpdate",
+            discount.key(),
+            None,
+            sale_channel_listing_update_operation(
+                &sale_id,
+                channel_ids.values().collect(),
+                discount.percentage,
+            ),
+        )?;
+        let products: Vec<cynic::Id> = discount
+            .products
+            .iter()
+            .map(|product| placeholder("product", product.product.id))
+            .collect();
+        let variants: Vec<cynic::Id> = discount
+            .variants
+            .iter()
+            .map(|variant| placeholder("variant", variant.product.id))
+            .collect();
+        let categories: Vec<cynic::Id> = discount
+            .categories
+            .iter()
+            .map(|category| placeholder("category", category.read().category.read().id))
+            .collect();
+        for catalogue in discounts::catalogue_inputs(&products, &variants, &categories) {
+            out.write(
+                "SaleCataloguesAdd",
+                discount.key(),
+                None,
+                sale_catalogues_add_operation(&sale_id, catalogue),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds every mutation the real run would send, in the same order, and writes them as ndjson
+/// to `path` instead of posting them to saleor. Nothing else is written, pictures show up with
+/// their old names as they aren't prepared
+pub fn dry_run(
+    data: &QueryAllResult,
+    customers: &[FinalCustomer],
+    orders: &[FinalOrder],
+    path: &str,
+) -> anyhow::Result<()> {
+    let mut out = DryRun {
+        file: std::fs::File::create(path)?,
+        count: 0,
+    };
+    let tax_class_id = placeholder("tax-class", &config().tax_class);
+    let warehouse_id = placeholder("warehouse", &config().warehouse);
+    let mut channel_ids = HashMap::from([(
+        config().channel.clone(),
+        placeholder("channel", &config().channel),
+    )]);
+    for price_channel in &config().price_channels {
+        channel_ids.insert(
+            price_channel.channel.clone(),
+            placeholder("channel", &price_channel.channel),
+        );
+    }
+    let attribute_ids = AttributeIds {
+        variant: config()
+            .variant_grouping
+            .as_ref()
+            .map(|grouping| placeholder("attribute", &grouping.attribute_slug)),
+        product: config()
+            .attributes
+            .iter()
+            .map(|a| {
+                let id = placeholder("attribute", &a.slug);
+                (a.slug.clone(), (id, a.input_type))
+            })
+            .collect(),
+    };
+
+    //    -----ATTRIBUTES-----
+    // the real run only creates the ones missing in saleor
+    if let Some(grouping) = &config().variant_grouping {
+        out.write(
+            "CreateAttribute",
+            &grouping.attribute_slug,
+            None,
+            attribute_create_operation(
+                &grouping.attribute_name,
+                &grouping.attribute_slug,
+                AttributeInputTypeEnum::Dropdown,
+            ),
+        )?;
+    }
+    for attribute in &config().attributes {
+        out.write(
+            "CreateAttribute",
+            &attribute.slug,
+            None,
+            attribute_create_operation(&attribute.name, &attribute.slug, attribute.input_type),
+        )?;
+    }
+
+    //    -----PRODUCT TYPES-----
+    let default_product_type = FinalProductType {
+        saleor_id: None,
+        name: "Základný typ".to_owned(),
+        attributes: vec![],
+    };
+    let product_types: Vec<_> = data.product_types.iter().map(|t| t.read()).collect();
+    let mut all_product_types = vec![&default_product_type];
+    all_product_types.extend(product_types.iter().map(|t| &**t));
+    for product_type in all_product_types {
+        let slug = product_type.name.to_lowercase().replace(" ", "-");
+        out.write(
+            "CreateProductType",
+            &product_type.name,
+            None,
+            product_type_create_operation(product_type, &slug, &tax_class_id, &attribute_ids),
+        )?;
+        let product_type_id = placeholder("product-type", &product_type.name);
+        if let Some(assign_operation) =
+            assign_attributes_operation(&product_type_id, &product_type.attributes, &attribute_ids)
+        {
+            out.write(
+                "ProductAttributeAssign",
+                &product_type.name,
+                None,
+                assign_operation,
+            )?;
+        }
+    }
+
+    //    -----CATEGORIES-----
+    let mut cat_slugs = HashSet::new();
+    for category in &data.categories {
+        let category = category.read();
+        let category_data = category.category.read();
+        if !cat_slugs.insert(category_data.slug.clone()) {
+            println!(
+                "category '{}: {}' slug '{}' is taken, the real run appends a random suffix",
+                category_data.id, category_data.name, category_data.slug
+            );
+        }
+        let parent_id = category
+            .parent_category
+            .as_ref()
+            .map(|parent| placeholder("category", parent.read().category.read().id));
+        let old_id = category_data.id.to_string();
+        let category_create_operation = CreateCategory::build(CreateCategoryVariables {
+            input: category_input(&category_data, &old_id),
+            parent_id: parent_id.as_ref(),
+        });
+        out.write(
+            "CreateCategory",
+            &old_id,
+            category.image.clone(),
+            category_create_operation,
+        )?;
+    }
+    for category in &data.categories {
+        let category = category.read();
+        let old_id = category.category.read().id;
+        let category_id = placeholder("category", old_id);
+        for translation in &category.translations {
+            out.write(
+                "CategoryTranslate",
+                old_id,
+                None,
+                category_translate_operation(&category_id, translation),
+            )?;
+        }
+    }
+
+    //    -----PRODUCTS-----
+    // (product, old id, category id, product type id, product type attributes)
+    let mut planned = vec![];
+    let mut product_slugs = HashSet::new();
+    for product in &data.products {
+        // the real run skips products whose category didn't make it into saleor
+        let Some(category) = &product.category else {
+            continue;
+        };
+        let category = category.read();
+        let old_id = product.product.id.to_string();
+        if !product_slugs.insert(product.slug.clone()) {
+            println!(
+                "product '{}: {}' slug '{}' is taken, the real run appends a random suffix",
+                old_id, product.product.name, product.slug
+            );
+        }
+        let category_id = placeholder("category", category.category.read().id);
+        let (product_type_id, type_attributes) = match &category.product_type {
+            Some(product_type) => {
+                let product_type = product_type.read();
+                (
+                    placeholder("product-type", &product_type.name),
+                    product_type.attributes.clone(),
+                )
+            }
+            None => (
+                placeholder("product-type", &default_product_type.name),
+                vec![],
+            ),
+        };
+        planned.push((
+            product,
+            old_id,
+            category_id,
+            product_type_id,
+            type_attributes,
+        ));
+    }
+
+    if let Some(bulk_size) = config().bulk_size {
+        for batch in planned.chunks(bulk_size.max(1)) {
+            let old_ids: Vec<&str> = batch.iter().map(|p| p.1.as_str()).collect();
+            let inputs = batch
+                .iter()
+                .map(
+                    |(product, old_id, category_id, product_type_id, type_attributes)| {
+                        product_bulk_create_input(
+                            Some(category_id),
+                            product,
+                            old_id,
+                            &tax_class_id,
+                            product_type_id,
+                            type_attributes,
+                            &attribute_ids,
+                            &warehouse_id,
+                            &channel_ids,
+                        )
+                    },
+                )
+                .collect();
+            out.write(
+                "ProductBulkCreate",
+                old_ids.join(","),
+                None,
+                product_bulk_create_operation(inputs),
+            )?;
+            for (product, old_id, ..) in batch {
+                let product_id = placeholder("product", old_id);
+                // Uploaded pictures don't fit into the bulk request, they follow one by one
+                if config().media_upload == MediaUpload::Multipart {
+                    for variant in std::iter::once(*product).chain(product.siblings.iter()) {
+                        for image in &variant.images {
+                            out.write(
+                                "ProductMediaCreate",
+                                variant.product.id,
+                                Some(image.clone()),
+                                product_media_create_operation(&product_id, variant, image),
+                            )?;
+                        }
+                    }
+                }
+                write_attachments(&mut out, product, &product_id)?;
+                write_translations(&mut out, product, &product_id)?;
+            }
+        }
+        return write_after_products(&mut out, data, customers, orders, &channel_ids, path);
+    }
+
+    for (product, old_id, category_id, product_type_id, type_attributes) in &planned {
+        let product_id = placeholder("product", old_id);
+        out.write(
+            "ProductCreate",
+            old_id,
+            None,
+            product_create_operation(
+                Some(category_id),
+                product,
+                old_id,
+                &tax_class_id,
+                product_type_id,
+                type_attributes,
+                &attribute_ids,
+            ),
+        )?;
+        out.write(
+            "ProductChannelListingUpdate",
+            old_id,
+            None,
+            product_channel_listing_update_operation(
+                &product_id,
+                product,
+                listed_channels(product, &channel_ids),
+            ),
+        )?;
+        for variant in std::iter::once(*product).chain(product.siblings.iter()) {
+            let old_id = variant.product.id.to_string();
+            let variant_id = placeholder("variant", &old_id);
+            out.write(
+                "VariantCreate",
+                &old_id,
+                None,
+                variant_create_operation(&product_id, variant, &warehouse_id, &attribute_ids),
+            )?;
+            let listings = variant_listings(variant, &channel_ids);
+            if !listings.is_empty() {
+                out.write(
+                    "VariantChannelListingUpdate",
+                    &old_id,
+                    None,
+                    variant_listing_update_operation(&variant_id, listings),
+                )?;
+            }
+            for image in &variant.images {
+                let upload = match config().media_upload {
+                    MediaUpload::Url => None,
+                    MediaUpload::Multipart => Some(image.clone()),
+                };
+                out.write(
+                    "ProductMediaCreate",
+                    &old_id,
+                    upload,
+                    product_media_create_operation(&product_id, variant, image),
+                )?;
+            }
+        }
+        write_attachments(&mut out, product, &product_id)?;
+        write_translations(&mut out, product, &product_id)?;
+    }
+    write_after_products(&mut out, data, customers, orders, &channel_ids, path)
+}
+
+/// Customers, orders and sales, the same after either way of creating products
+fn write_after_products(
+    out: &mut DryRun,
+    data: &QueryAllResult,
+    customers: &[FinalCustomer],
+    orders: &[FinalOrder],
+    channel_ids: &HashMap<String, cynic::Id>,
+    path: &str,
+) -> anyhow::Result<()> {
+    let warehouse_id = placeholder("warehouse", &config().warehouse);
+    write_customers(out, customers)?;
+    write_orders(out, orders, &data.products, customers, &warehouse_id)?;
+    if config().discounts.is_some() {
+        write_discounts(out, data, channel_ids)?;
+    }
+    println!("Wrote {} planned operations to {}", out.count, path);
+    Ok(())
+}
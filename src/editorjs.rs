@@ -0,0 +1,470 @@
+use rand::distributions::{Alphanumeric, DistString};
+use scraper::{ElementRef, Html, Node};
+use serde_json::{json, Value};
+
+/// What the saleor dashboard ships with
+pub const EDITORJS_VERSION: &str = "2.24.3";
+
+/// Whole element including its content is thrown away
+const DROPPED: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "form", "input", "button", "select",
+    "textarea", "noscript", "head", "title", "meta", "link", "svg", "video", "audio",
+];
+
+/// Editor.js document out of the old html descriptions. Headers, lists, tables, quotes and
+/// pictures get their own blocks, everything else ends up in paragraphs. Inside the blocks only
+/// bold, italic, underline, links and line breaks survive, no classes and no inline styles.
+/// Text without any markup gets its blank lines as paragraph breaks, its entities are decoded
+/// first so they don't end up escaped twice. `time` is the document's timestamp and `block_id`
+/// names each block, see `random_id`
+pub fn from_html(html: &str, time: i64, block_id: impl FnMut() -> String) -> Value {
+    let html = match html.contains('<') {
+        true => html.to_owned(),
+        false => html
+            .split("\n\n")
+            .map(|paragraph| {
+                let paragraph = html_escape::decode_html_entities(paragraph.trim());
+                format!(
+                    "<p>{}</p>",
+                    html_escape::encode_text(&paragraph).replace('\n', "<br>")
+                )
+            })
+            .collect(),
+    };
+    let fragment = Html::parse_fragment(&html);
+    let mut converter = Converter {
+        blocks: vec![],
+        inline: String::new(),
+        inline_only: false,
+        block_id,
+    };
+    converter.children(fragment.root_element());
+    converter.flush();
+    json!({
+        "time": time,
+        "blocks": converter.blocks,
+        "version": EDITORJS_VERSION,
+    })
+}
+
+/// Block id like the ones the dashboard makes
+pub fn random_id() -> String {
+    //INFO: MAGIC NUMBER!
+    Alphanumeric.sample_string(&mut rand::thread_rng(), 10)
+}
+
+struct Converter<F> {
+    blocks: Vec<Value>,
+    /// Inline html of the paragraph being collected
+    inline: String,
+    /// Inside a block that can only hold inline html, nested blocks become line breaks there
+    inline_only: bool,
+    block_id: F,
+}
+
+impl<F: FnMut() -> String> Converter<F> {
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.starts_with(char::is_whitespace) && !collapsed.is_empty() {
+            collapsed.insert(0, ' ');
+        }
+        if text.ends_with(char::is_whitespace) {
+            collapsed.push(' ');
+        }
+        self.inline.push_str(&html_escape::encode_text(&collapsed));
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if DROPPED.contains(&name) {
+            return;
+        }
+        if self.inline_only && is_block(name) {
+            if name != "img" {
+                self.line_break();
+                self.children(element);
+                self.line_break();
+            }
+            return;
+        }
+        match name {
+            "br" => self.inline.push_str("<br>"),
+            "b" | "strong" => self.wrap("b", element),
+            "i" | "em" => self.wrap("i", element),
+            "u" => self.wrap("u", element),
+            "a" => match element.value().attr("href").filter(|href| safe_url(href)) {
+                Some(href) => {
+                    self.inline.push_str(&format!(
+                        "<a href=\"{}\">",
+                        html_escape::encode_double_quoted_attribute(href.trim())
+                    ));
+                    self.children(element);
+                    self.inline.push_str("</a>");
+                }
+                None => self.children(element),
+            },
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let text = self.inline_of(element);
+                if has_text(&text) {
+                    // the digit in h1..h6
+                    let level = name[1..].parse::<u8>().unwrap_or(2);
+                    self.block("header", json!({ "text": text, "level": level }));
+                }
+            }
+            "ul" | "ol" => {
+                let items: Vec<String> = element
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|child| child.value().name() == "li")
+                    .map(|item| self.inline_of(item))
+                    .filter(|item| has_text(item))
+                    .collect();
+                if !items.is_empty() {
+                    let style = match name {
+                        "ol" => "ordered",
+                        _ => "unordered",
+                    };
+                    self.block("list", json!({ "style": style, "items": items }));
+                }
+            }
+            "table" => self.table(element),
+            "blockquote" => {
+                let text = self.inline_of(element);
+                if has_text(&text) {
+                    self.block(
+                        "quote",
+                        json!({ "text": text, "caption": "", "alignment": "left" }),
+                    );
+                }
+            }
+            "img" => {
+                let Some(src) = element.value().attr("src").filter(|src| safe_url(src)) else {
+                    return;
+                };
+                let caption = element.value().attr("alt").unwrap_or_default();
+                self.block(
+                    "image",
+                    json!({
+                        "file": { "url": src.trim() },
+                        "caption": html_escape::encode_text(caption.trim()),
+                        "withBorder": false,
+                        "stretched": false,
+                        "withBackground": false,
+                    }),
+                );
+            }
+            "hr" => self.flush(),
+            _ if is_block(name) => {
+                self.flush();
+                self.children(element);
+                self.flush();
+            }
+            // span, font and other wrappers only lose their attributes
+            _ => self.children(element),
+        }
+    }
+
+    fn table(&mut self, table: ElementRef) {
+        let mut with_headings = false;
+        let mut content: Vec<Vec<String>> = vec![];
+        for row in table
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|e| e.value().name() == "tr")
+        {
+            let cells: Vec<ElementRef> = row
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .collect();
+            if content.is_empty() {
+                with_headings =
+                    !cells.is_empty() && cells.iter().all(|cell| cell.value().name() == "th");
+            }
+            content.push(cells.into_iter().map(|cell| self.inline_of(cell)).collect());
+        }
+        content.retain(|row| row.iter().any(|cell| has_text(cell)));
+        if content.is_empty() {
+            return;
+        }
+        // Editor.js wants every row as wide as the widest one
+        let width = content.iter().map(Vec::len).max().unwrap_or_default();
+        for row in content.iter_mut() {
+            row.resize(width, String::new());
+        }
+        self.block(
+            "table",
+            json!({ "withHeadings": with_headings, "content": content }),
+        );
+    }
+
+    fn wrap(&mut self, tag: &str, element: ElementRef) {
+        self.inline.push_str(&format!("<{}>", tag));
+        self.children(element);
+        self.inline.push_str(&format!("</{}>", tag));
+    }
+
+    /// Inline html of the element's content, without touching the paragraph being collected
+    fn inline_of(&mut self, element: ElementRef) -> String {
+        let outer = std::mem::take(&mut self.inline);
+        let outer_inline_only = std::mem::replace(&mut self.inline_only, true);
+        self.children(element);
+        self.inline_only = outer_inline_only;
+        let inner = std::mem::replace(&mut self.inline, outer);
+        trim_inline(&inner)
+    }
+
+    fn line_break(&mut self) {
+        if has_text(&self.inline) && !self.inline.trim_end().ends_with("<br>") {
+            self.inline.push_str("<br>");
+        }
+    }
+
+    /// Ends the paragraph being collected
+    fn flush(&mut self) {
+        let text = trim_inline(&std::mem::take(&mut self.inline));
+        if has_text(&text) {
+            self.block("paragraph", json!({ "text": text }));
+        }
+    }
+
+    fn block(&mut self, kind: &str, data: Value) {
+        if kind != "paragraph" {
+            self.flush();
+        }
+        let id = (self.block_id)();
+        self.blocks
+            .push(json!({ "id": id, "type": kind, "data": data }));
+    }
+}
+
+/// Without whitespace and line breaks around it
+fn trim_inline(text: &str) -> String {
+    let mut text = text.trim();
+    loop {
+        let trimmed = text
+            .trim_start_matches("<br>")
+            .trim_end_matches("<br>")
+            .trim();
+        if trimmed.len() == text.len() {
+            return trimmed.to_owned();
+        }
+        text = trimmed;
+    }
+}
+
+/// Become their own block, or a line break when inside a header, list item, cell or quote
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "main"
+            | "header"
+            | "footer"
+            | "aside"
+            | "center"
+            | "figure"
+            | "figcaption"
+            | "pre"
+            | "address"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "li"
+            | "hr"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "ul"
+            | "ol"
+            | "table"
+            | "blockquote"
+            | "img"
+    )
+}
+
+/// Anything but tags and whitespace
+fn has_text(html: &str) -> bool {
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag && !c.is_whitespace() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// No `javascript:` and such in links and pictures
+fn safe_url(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    !url.is_empty()
+        && !url.starts_with("javascript:")
+        && !url.starts_with("vbscript:")
+        && !url.starts_with("data:")
+}
+
+/// Plain text of every block in an Editor.js document, for places that can't take markup
+pub fn text(json: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let mut texts: Vec<&str> = vec![];
+    for block in value["blocks"].as_array()? {
+        let data = &block["data"];
+        texts.extend(data["text"].as_str());
+        if let Some(items) = data["items"].as_array() {
+            texts.extend(items.iter().filter_map(Value::as_str));
+        }
+        if let Some(rows) = data["content"].as_array() {
+            texts.extend(
+                rows.iter()
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .filter_map(Value::as_str),
+            );
+        }
+    }
+    Some(texts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `b1`, `b2`... so the expected blocks can be written out
+    fn blocks(html: &str) -> Value {
+        let mut n = 0;
+        let document = from_html(html, 0, || {
+            n += 1;
+            format!("b{}", n)
+        });
+        assert_eq!(document["time"], 0);
+        assert_eq!(document["version"], EDITORJS_VERSION);
+        document["blocks"].clone()
+    }
+
+    #[test]
+    fn dropped_tags_lose_their_content() {
+        assert_eq!(
+            blocks("<p>Hi<script>alert(1)</script></p><style>p{}</style><iframe>x</iframe><p>there</p>"),
+            json!([
+                { "id": "b1", "type": "paragraph", "data": { "text": "Hi" } },
+                { "id": "b2", "type": "paragraph", "data": { "text": "there" } },
+            ])
+        );
+    }
+
+    #[test]
+    fn attributes_are_gone() {
+        assert_eq!(
+            blocks(
+                r#"<h2 class="x" style="color:red"><span style="a">Title</span></h2><blockquote>q<br>r</blockquote>"#
+            ),
+            json!([
+                { "id": "b1", "type": "header", "data": { "text": "Title", "level": 2 } },
+                {
+                    "id": "b2",
+                    "type": "quote",
+                    "data": { "text": "q<br>r", "caption": "", "alignment": "left" },
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn javascript_links_and_pictures() {
+        assert_eq!(
+            blocks(concat!(
+                r#"<p><a href="javascript:alert(1)">click</a> <a href=" https://shop.sk/a?b=1&amp;c=2 ">ok</a></p>"#,
+                r#"<img src="JavaScript:x"><img src="/p.jpg" alt="A &amp; B">"#,
+            )),
+            json!([
+                {
+                    "id": "b1",
+                    "type": "paragraph",
+                    "data": { "text": r#"click <a href="https://shop.sk/a?b=1&amp;c=2">ok</a>"# },
+                },
+                {
+                    "id": "b2",
+                    "type": "image",
+                    "data": {
+                        "file": { "url": "/p.jpg" },
+                        "caption": "A &amp; B",
+                        "withBorder": false,
+                        "stretched": false,
+                        "withBackground": false,
+                    },
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn nested_blocks_in_list_items() {
+        assert_eq!(
+            blocks("<ul><li>one<p>two</p></li><li><table><tr><td>x</td></tr></table></li><li> </li></ul>"),
+            json!([{
+                "id": "b1",
+                "type": "list",
+                "data": { "style": "unordered", "items": ["one<br>two", "x"] },
+            }])
+        );
+    }
+
+    #[test]
+    fn ragged_table_with_nested_blocks_in_cells() {
+        assert_eq!(
+            blocks("<table><tr><th>A</th><th>B</th></tr><tr><td><div>c</div><p>d</p></td></tr><tr><td></td></tr></table>"),
+            json!([{
+                "id": "b1",
+                "type": "table",
+                "data": { "withHeadings": true, "content": [["A", "B"], ["c<br>d", ""]] },
+            }])
+        );
+    }
+
+    #[test]
+    fn escaped_text_stays_text() {
+        assert_eq!(
+            blocks("<p>price &lt;b&gt; 5</p>"),
+            json!([{ "id": "b1", "type": "paragraph", "data": { "text": "price &lt;b&gt; 5" } }])
+        );
+        assert_eq!(
+            blocks("price &lt; 5 &amp; more\n\nsecond\nline"),
+            json!([
+                { "id": "b1", "type": "paragraph", "data": { "text": "price &lt; 5 &amp; more" } },
+                { "id": "b2", "type": "paragraph", "data": { "text": "second<br>line" } },
+            ])
+        );
+    }
+
+    #[test]
+    fn plain_text_of_the_blocks() {
+        let document = from_html(
+            "<h1>A</h1><ul><li>b</li></ul><table><tr><td>c</td></tr></table>",
+            0,
+            random_id,
+        );
+        assert_eq!(text(&document.to_string()).as_deref(), Some("A b c"));
+    }
+}
@@ -1,21 +1,23 @@
-use crate::gql_queries::{self, ProductType};
+use crate::config::{config, AttributeSource, GroupBy, VariantGrouping};
+use crate::gql_queries::{self, LanguageCodeEnum, ProductType};
 
 use anyhow::Context;
 use chrono::NaiveDateTime;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use sqlx::types::BigDecimal;
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
 
 pub struct QueryAllResult {
-    pub categories: Vec<Rc<RefCell<FinalCategory>>>,
+    pub categories: Vec<Arc<RwLock<FinalCategory>>>,
     pub products: Vec<FinalProduct>,
-    pub product_types: Vec<Rc<RefCell<FinalProductType>>>,
+    pub product_types: Vec<Arc<RwLock<FinalProductType>>>,
 }
 pub async fn query_all() -> anyhow::Result<QueryAllResult> {
     let pool = sqlx::mysql::MySqlPoolOptions::new()
         .max_connections(20)
-        .connect(gql_queries::SQL_Endpoint)
+        .connect(&config().sql_endpoint)
         .await?;
 
     //INFO: MAGIC NUMBER!
@@ -48,6 +50,14 @@ pub async fn query_all() -> anyhow::Result<QueryAllResult> {
         .fetch_all(&pool)
         .await?;
 
+    let product_prices: Vec<ProductPrices> = sqlx::query_as!(
+        ProductPrices,
+        //INFO: MAGIC NUMBER!
+        "SELECT * FROM product_prices LIMIT 1000000;"
+    )
+    .fetch_all(&pool)
+    .await?;
+
     let mut final_product_types = vec![];
     let mut final_categories =
         FinalCategory::from_categories(categories, files.clone(), &mut final_product_types).await;
@@ -59,6 +69,7 @@ pub async fn query_all() -> anyhow::Result<QueryAllResult> {
         category_product,
         file_product,
         files,
+        product_prices,
     )
     .await;
 
@@ -71,7 +82,7 @@ pub async fn query_all() -> anyhow::Result<QueryAllResult> {
         let mut depth_b = 0;
         for (mut prev_parent, depth) in [(a.clone(), &mut depth_a), (b.clone(), &mut depth_b)] {
             loop {
-                if let Some(curr_parent) = prev_parent.clone().borrow().parent_category.clone() {
+                if let Some(curr_parent) = prev_parent.clone().read().parent_category.clone() {
                     prev_parent = curr_parent;
                     *depth += 1;
                 } else {
@@ -89,11 +100,133 @@ pub async fn query_all() -> anyhow::Result<QueryAllResult> {
     })
 }
 
+/// Old shop accounts with their addresses, deleted ones left out
+pub async fn query_customers() -> anyhow::Result<Vec<FinalCustomer>> {
+    let pool = sqlx::mysql::MySqlPoolOptions::new()
+        .max_connections(20)
+        .connect(&config().sql_endpoint)
+        .await?;
+
+    let users: Vec<User> = sqlx::query_as!(
+        User,
+        //INFO: MAGIC NUMBER!
+        "SELECT id, email, first_name, last_name, deleted_at FROM users LIMIT 1000000;"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let addresses: Vec<UserAddress> = sqlx::query_as!(
+        UserAddress,
+        //INFO: MAGIC NUMBER!
+        "SELECT id, user_id, first_name, last_name, company, street, city, zip, country_id, phone FROM addresses LIMIT 1000000;"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let mut addresses_by_user: HashMap<u32, Vec<UserAddress>> = HashMap::new();
+    for address in addresses {
+        addresses_by_user
+            .entry(address.user_id)
+            .or_default()
+            .push(address);
+    }
+    let customers = users
+        .into_iter()
+        .filter(|user| user.deleted_at.is_none())
+        .map(|user| {
+            let mut addresses = addresses_by_user.remove(&user.id).unwrap_or_default();
+            // biggest id = newest
+            addresses.sort_unstable_by(|a, b| b.id.cmp(&a.id));
+            FinalCustomer {
+                email: user.email.trim().to_lowercase(),
+                user,
+                addresses,
+            }
+        })
+        .collect();
+    Ok(customers)
+}
+
+pub async fn query_orders() -> anyhow::Result<Vec<FinalOrder>> {
+    let pool = sqlx::mysql::MySqlPoolOptions::new()
+        .max_connections(20)
+        .connect(&config().sql_endpoint)
+        .await?;
+
+    let orders: Vec<Order> = sqlx::query_as!(
+        Order,
+        //INFO: MAGIC NUMBER!
+        "SELECT id, number, user_id, email, status_id, note, first_name, last_name, company, street, city, zip, country_id, phone, delivery_first_name, delivery_last_name, delivery_company, delivery_street, delivery_city, delivery_zip, delivery_country_id, delivery_phone, shipping_name, shipping_price, shipping_price_with_vat, created_at, deleted_at FROM orders LIMIT 1000000;"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let lines: Vec<OrderLine> = sqlx::query_as!(
+        OrderLine,
+        //INFO: MAGIC NUMBER!
+        "SELECT id, order_id, product_id, name, code, quantity, price, price_with_vat, vat FROM order_product LIMIT 10000000;"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let mut lines_by_order: HashMap<u32, Vec<OrderLine>> = HashMap::new();
+    for line in lines {
+        lines_by_order.entry(line.order_id).or_default().push(line);
+    }
+    let orders = orders
+        .into_iter()
+        .filter(|order| order.deleted_at.is_none())
+        .map(|order| {
+            let mut lines = lines_by_order.remove(&order.id).unwrap_or_default();
+            lines.sort_unstable_by_key(|line| line.id);
+            let billing = UserAddress {
+                id: order.id,
+                user_id: order.user_id.unwrap_or_default(),
+                first_name: order.first_name.clone(),
+                last_name: order.last_name.clone(),
+                company: order.company.clone(),
+                street: order.street.clone(),
+                city: order.city.clone(),
+                zip: order.zip.clone(),
+                country_id: order.country_id.clone(),
+                phone: order.phone.clone(),
+            };
+            // The old checkout left the delivery columns empty when it was the billing address
+            let shipping = order
+                .delivery_street
+                .as_deref()
+                .filter(|street| !street.trim().is_empty())
+                .map(|_| UserAddress {
+                    id: order.id,
+                    user_id: order.user_id.unwrap_or_default(),
+                    first_name: order.delivery_first_name.clone(),
+                    last_name: order.delivery_last_name.clone(),
+                    company: order.delivery_company.clone(),
+                    street: order.delivery_street.clone(),
+                    city: order.delivery_city.clone(),
+                    zip: order.delivery_zip.clone(),
+                    country_id: order.delivery_country_id.clone(),
+                    phone: order.delivery_phone.clone(),
+                });
+            FinalOrder {
+                order,
+                billing,
+                shipping,
+                lines,
+            }
+        })
+        .collect();
+    Ok(orders)
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct YamlCategories {
     pub meno_typu: String,
     pub meno: String,
     id: u32,
+    /// Attribute slugs (from `attributes` in the config) the product type `meno_typu` gets
+    #[serde(default)]
+    pub atributy: Vec<String>,
     pub podkategorie: Vec<Self>,
 }
 
@@ -106,8 +239,7 @@ enum Maybe<T> {
 impl YamlCategories {
     pub fn load() -> Self {
         let data =
-            //INFO: MAGIC NUMBER!
-            std::fs::read_to_string("./filled_out_kategorie.yaml").expect("Unable to read file");
+            std::fs::read_to_string(&config().category_mapping).expect("Unable to read file");
         serde_yaml::from_str(&data).unwrap()
     }
 
@@ -136,23 +268,39 @@ impl YamlCategories {
             }
         }
     }
+    /// Attributes of a product type, from every category it's named in
+    fn type_attributes(&self, type_name: &str, attributes: &mut Vec<String>) {
+        if self.meno_typu == type_name {
+            for attribute in &self.atributy {
+                if !attributes.contains(attribute) {
+                    attributes.push(attribute.clone());
+                }
+            }
+        }
+        for podcat in &self.podkategorie {
+            podcat.type_attributes(type_name, attributes);
+        }
+    }
+
     pub fn find_product_type(
         &self,
         category_id: u32,
-        product_types: &mut Vec<Rc<RefCell<FinalProductType>>>,
-    ) -> Option<Rc<RefCell<FinalProductType>>> {
+        product_types: &mut Vec<Arc<RwLock<FinalProductType>>>,
+    ) -> Option<Arc<RwLock<FinalProductType>>> {
         let type_name = match self.search(category_id) {
             Maybe::Some(r) => Some(r),
             _ => None,
         };
         if let Some(type_name) = type_name {
-            if let Some(product_type) = product_types.iter().find(|t| t.borrow().name == type_name)
-            {
+            if let Some(product_type) = product_types.iter().find(|t| t.read().name == type_name) {
                 return Some(product_type.clone());
             }
-            product_types.push(Rc::new(RefCell::new(FinalProductType {
+            let mut attributes = vec![];
+            self.type_attributes(&type_name, &mut attributes);
+            product_types.push(Arc::new(RwLock::new(FinalProductType {
                 name: type_name,
                 saleor_id: None,
+                attributes,
             })))
         }
         None
@@ -177,20 +325,59 @@ pub struct FileProduct {
 pub struct FinalProduct {
     pub product: Product,
     pub saleor_id: Option<cynic::Id>,
-    pub category: Option<Rc<RefCell<FinalCategory>>>,
+    pub category: Option<Arc<RwLock<FinalCategory>>>,
+    /// Picture file names, relative to `media_root`, or to the prepared folder after
+    /// `images::prepare_images`
     pub images: Vec<String>,
     pub price: Option<String>,
+    /// Prices from `product_prices` for channels in the `price_channels` config
+    pub prices: Vec<ChannelPrice>,
     pub SKU: String,
     pub slug: String,
+    /// Slug from the old shop's urls, `slug` changes when the product heads a family or its slug
+    /// is taken
+    pub old_slug: String,
+    /// Other old products that become variants of this one, see `variant_grouping`
+    pub siblings: Vec<FinalProduct>,
+    /// Name and attribute value of this product's variant if it's part of a family
+    pub variant_value: Option<String>,
+    /// (attribute slug, value) for every configured attribute the old product has a value for
+    pub attributes: Vec<(String, String)>,
+    /// (file name relative to `media_root`, mime type) of the old files that aren't pictures,
+    /// datasheets, manuals and such
+    pub attachments: Vec<(String, String)>,
+    /// Texts in the other languages from `languages`, pushed after the product exists
+    pub translations: Vec<Translation>,
+    /// Old timestamps as rfc3339, for the publication dates and the metadata
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Texts of a product or category in one of the configured `languages`, html like the main ones
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub language: LanguageCodeEnum,
+    pub name: String,
+    pub short_description: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelPrice {
+    /// Channel slug
+    pub channel: String,
+    pub price: String,
+    pub cost_price: Option<String>,
 }
 
 impl FinalProduct {
     pub async fn from_products(
         products: Vec<Product>,
-        categories: &Vec<Rc<RefCell<FinalCategory>>>,
+        categories: &Vec<Arc<RwLock<FinalCategory>>>,
         rel_category_product: Vec<CategoryProduct>,
         file_products: Vec<FileProduct>,
         files: Vec<File>,
+        product_prices: Vec<ProductPrices>,
     ) -> Vec<Self> {
         //INFO: MAGIC NUMBER!
         let slugify = regex::Regex::new(r###"[^a-zA-Z0-9-]+"###).unwrap();
@@ -198,7 +385,7 @@ impl FinalProduct {
         //filter out products or test names or deleted while mapping
         let pool = sqlx::mysql::MySqlPoolOptions::new()
             .max_connections(20)
-            .connect(gql_queries::SQL_Endpoint)
+            .connect(&config().sql_endpoint)
             .await
             .expect("Failed creating sql connection");
 
@@ -218,33 +405,64 @@ impl FinalProduct {
                         "-",
                     )
                     .to_string();
+                let created_at = product.created_at.map(|date| date.to_rfc3339());
+                let updated_at = product.updated_at.map(|date| date.to_rfc3339());
                 Some(FinalProduct {
                     product,
                     saleor_id: None,
                     category: None,
                     images: Vec::new(),
                     price: None,
+                    prices: Vec::new(),
                     SKU: sku,
+                    old_slug: slug.clone(),
                     slug,
+                    siblings: Vec::new(),
+                    variant_value: None,
+                    attributes: Vec::new(),
+                    attachments: Vec::new(),
+                    translations: Vec::new(),
+                    created_at,
+                    updated_at,
                 })
             })
             .collect();
 
+        // All texts in one go, a query per product takes ages on the old catalogue
+        let mut texts_by_product: HashMap<u32, Vec<ProductsTexts>> = HashMap::new();
+        if !config().languages.is_empty()
+            || final_products.iter().any(|p| p.product.name.is_empty())
+        {
+            let all_texts = sqlx::query_as!(ProductsTexts, "SELECT * FROM products_texts;")
+                .fetch_all(&pool)
+                .await
+                .expect("failed fetching product_texts");
+            for text in all_texts {
+                texts_by_product
+                    .entry(text.product_id)
+                    .or_default()
+                    .push(text);
+            }
+        }
         for p in final_products.iter_mut() {
-            if p.product.name.is_empty() {
-                //INFO: MAGIC NUMBER!
-                let product_texts_q = sqlx::query_as!(
-                    ProductsTexts,
-                    "SELECT * FROM products_texts WHERE product_id = ?;",
-                    p.product.id
-                )
-                .fetch_all(&pool);
-
-                let mut product_texts = product_texts_q
-                    .await
-                    .expect("failed fetching product_texts");
+            if p.product.name.is_empty() || !config().languages.is_empty() {
+                let mut product_texts = texts_by_product.remove(&p.product.id).unwrap_or_default();
+                // newest first, default language ones before the rest
                 product_texts.sort_unstable_by(|t, n| n.updated_at.cmp(&t.updated_at));
-                p.product.name = product_texts[0].name.clone();
+                product_texts.sort_by_key(|t| t.language_id != config().default_language_id);
+                if p.product.name.is_empty() {
+                    if let Some(text) = product_texts.get(0) {
+                        p.product.name = text.name.clone();
+                    }
+                }
+                p.translations = translations(&product_texts, |t| {
+                    (
+                        t.language_id,
+                        &t.name,
+                        t.short_description.as_str(),
+                        &t.description,
+                    )
+                });
             }
         }
 
@@ -264,7 +482,7 @@ impl FinalProduct {
             if let Some(category) = category_matches.get(0) {
                 product.category = categories
                     .iter()
-                    .find(|&cat| cat.borrow().category.borrow().id == category.category_id)
+                    .find(|&cat| cat.read().category.read().id == category.category_id)
                     .cloned();
             }
         }
@@ -279,8 +497,63 @@ impl FinalProduct {
             //Fix descriptions
             product.product.description =
                 gql_queries::Jsonstring::purify_old_json(&product.product.description);
-            product.product.short_description =
+            // The short one only ends up as metadata (and seo), as text, not markup
+            let short_description =
                 gql_queries::Jsonstring::purify_old_json(&product.product.short_description);
+            product.product.short_description =
+                html_escape::decode_html_entities(&short_description).into_owned();
+        }
+
+        // Attribute values straight from the old product columns
+        for product in final_products.iter_mut() {
+            for attribute in &config().attributes {
+                let by_id = |id: Option<i64>| {
+                    id.map(|id| match attribute.values.get(&id) {
+                        Some(text) => text.clone(),
+                        None => id.to_string(),
+                    })
+                };
+                let value = match attribute.source {
+                    AttributeSource::Weight => product.product.weight.map(|w| w.to_string()),
+                    AttributeSource::Quantity => product.product.quantity.map(|q| q.to_string()),
+                    AttributeSource::Unit => by_id(product.product.unit_id.map(i64::from)),
+                    AttributeSource::AvailabilityText => {
+                        by_id(product.product.availability_text_id.map(i64::from))
+                    }
+                };
+                if let Some(value) = value {
+                    product.attributes.push((attribute.slug.clone(), value));
+                }
+            }
+        }
+
+        // Pair old store/country prices to the channels they're mapped to
+        let product_prices: HashMap<(u32, u32, &str), &ProductPrices> = product_prices
+            .iter()
+            .map(|p| ((p.product_id, p.store_id, p.country_id.as_str()), p))
+            .collect();
+        for product in final_products.iter_mut() {
+            for price_channel in &config().price_channels {
+                let key = (
+                    product.product.id,
+                    price_channel.store_id,
+                    price_channel.country_id.as_str(),
+                );
+                let Some(row) = product_prices.get(&key) else {
+                    continue;
+                };
+                let (price, cost_price) = match price_channel.without_vat {
+                    true => (&row.price, &row.wholesale_price),
+                    false => (&row.price_with_vat, &row.wholesale_price_with_vat),
+                };
+                product.prices.push(ChannelPrice {
+                    channel: price_channel.channel.clone(),
+                    price: price.to_string(),
+                    cost_price: price_channel
+                        .wholesale_as_cost_price
+                        .then(|| cost_price.to_string()),
+                });
+            }
         }
 
         // Find images belonging to the product
@@ -291,29 +564,35 @@ impl FinalProduct {
             {
                 if let Some(file_match) = files.iter().find(|file| file.id == file_product.file_id)
                 {
-                    if file_match.mime_type.contains("pdf") {
+                    if !file_match.mime_type.starts_with("image") {
+                        if !config().media_root.join(&file_match.name).is_file() {
+                            println!(
+                                "attachment '{}' of product '{}: {}' isn't in {}, skipping it",
+                                file_match.name,
+                                match_product.product.id,
+                                match_product.product.name,
+                                config().media_root.display()
+                            );
+                            continue;
+                        }
+                        match_product
+                            .attachments
+                            .push((file_match.name.clone(), file_match.mime_type.clone()));
                         continue;
                     }
-                    /*
-                    let mut file_name = file_match.name.clone();
-                    if file_match.name.contains(".webp") {
-                        if let Some(name) = file_match.name.rsplit_once(".") {
-                            let name = name.0.to_ascii_lowercase();
-                            if name.contains(".png")
-                                || name.contains(".jpg")
-                                || name.contains(".jpeg")
-                            {
-                                file_name = name
-                            }
-                        }
+                    // Checked up front, so products don't end up with media that can never be
+                    // fetched or uploaded
+                    if !config().media_root.join(&file_match.name).is_file() {
+                        println!(
+                            "picture '{}' of product '{}: {}' isn't in {}, skipping it",
+                            file_match.name,
+                            match_product.product.id,
+                            match_product.product.name,
+                            config().media_root.display()
+                        );
+                        continue;
                     }
-                    */
-                    let ip_address = local_ip_address::local_ip()
-                        .expect("Failed finding local IP. Are you offline?");
-                    let file_name =
-            //INFO: MAGIC NUMBER!
-                        format!("http://{}:38008/products/{}", ip_address, file_match.name);
-                    match_product.images.push(file_name);
+                    match_product.images.push(file_match.name.clone());
                 }
             }
         }
@@ -334,10 +613,133 @@ impl FinalProduct {
                 skus.push(new_sku);
             }
         }
-        final_products
+        Self::group_variants(final_products, config().variant_grouping.as_ref(), &slugify)
+    }
+
+    /// Merges sibling products into the first product of their family by the `grouping` rules.
+    /// Only products in the same category can be siblings, a family of one stays as it was
+    fn group_variants(
+        products: Vec<Self>,
+        grouping: Option<&VariantGrouping>,
+        slugify: &regex::Regex,
+    ) -> Vec<Self> {
+        let Some(grouping) = grouping else {
+            return products;
+        };
+        let rules: Vec<(GroupBy, &regex::Regex)> = grouping
+            .rules
+            .iter()
+            .map(|rule| (rule.by, &rule.pattern.0))
+            .collect();
+
+        let mut grouped: Vec<Self> = Vec::new();
+        // (rule, base, category) -> index in grouped and the family name
+        let mut families: HashMap<(usize, String, Option<u32>), (usize, Option<String>)> =
+            HashMap::new();
+        for mut product in products {
+            let matched = rules.iter().enumerate().find_map(|(i, (by, pattern))| {
+                let text = match by {
+                    GroupBy::Sku => &product.product.code,
+                    GroupBy::Name => &product.product.name,
+                };
+                let captures = pattern.captures(text)?;
+                let base = captures.name("base")?.as_str().trim().to_owned();
+                let variant = captures.name("variant")?.as_str().trim().to_owned();
+                Some((i, *by, base, variant))
+            });
+            let Some((rule, by, base, variant)) = matched else {
+                grouped.push(product);
+                continue;
+            };
+            let category = product
+                .category
+                .as_ref()
+                .map(|c| c.read().category.read().id);
+            product.variant_value = Some(variant);
+            match families.get(&(rule, base.clone(), category)) {
+                Some((i, _)) => grouped[*i].siblings.push(product),
+                None => {
+                    let name = (by == GroupBy::Name).then(|| base.clone());
+                    families.insert((rule, base, category), (grouped.len(), name));
+                    grouped.push(product);
+                }
+            }
+        }
+
+        for (i, name) in families.into_values() {
+            let product = &mut grouped[i];
+            if product.siblings.is_empty() {
+                product.variant_value = None;
+                continue;
+            }
+            println!(
+                "grouped {} products into '{}'",
+                product.siblings.len() + 1,
+                product.product.name
+            );
+            // the product is named after the whole family, not its first colour
+            if let Some(name) = name {
+                product.slug = slugify
+                    .replace_all(
+                        deunicode::deunicode(name.as_str())
+                            .to_ascii_lowercase()
+                            .trim(),
+                        "-",
+                    )
+                    .to_string();
+                product.product.name = name;
+            }
+        }
+        grouped
+    }
+
+    /// Price per channel slug. Rows mapped from `product_prices` win, the main channel falls back
+    /// to `retail_price_with_iva`
+    pub fn channel_prices(&self) -> Vec<ChannelPrice> {
+        let mut prices = self.prices.clone();
+        if !prices.iter().any(|p| p.channel == config().channel) {
+            if let Some(price) = &self.price {
+                prices.push(ChannelPrice {
+                    channel: config().channel.clone(),
+                    price: price.clone(),
+                    cost_price: None,
+                });
+            }
+        }
+        prices
     }
 }
 
+/// Newest texts of every configured non-default language, `texts` have to be sorted newest first.
+/// `fields` picks (language_id, name, short description, description) out of a row
+fn translations<T>(
+    texts: &[T],
+    fields: impl Fn(&T) -> (u32, &String, &str, &String),
+) -> Vec<Translation> {
+    let mut translations: Vec<Translation> = vec![];
+    for text in texts {
+        let (language_id, name, short_description, description) = fields(text);
+        if language_id == config().default_language_id {
+            continue;
+        }
+        let Some(&language) = config().languages.get(&language_id) else {
+            continue;
+        };
+        if translations.iter().any(|t| t.language == language) {
+            continue;
+        }
+        translations.push(Translation {
+            language,
+            name: name.clone(),
+            short_description: gql_queries::Jsonstring::purify_old_json(
+                &short_description.to_owned(),
+            ),
+            description: gql_queries::Jsonstring::purify_old_json(description),
+        });
+    }
+    translations
+}
+
 pub struct CategoryTexts {
     id: i32,
     name: String,
@@ -359,40 +761,138 @@ pub struct ProductsTexts {
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+#[derive(Debug)]
+pub struct User {
+    pub id: u32,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Also the billing and delivery address of an old order, then `id` is the order's
+#[derive(Debug)]
+pub struct UserAddress {
+    pub id: u32,
+    pub user_id: u32,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub company: Option<String>,
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub zip: Option<String>,
+    /// Two letter country code
+    pub country_id: Option<String>,
+    pub phone: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct FinalCustomer {
+    pub user: User,
+    /// Trimmed and lowercased, saleor matches emails exactly
+    pub email: String,
+    /// Newest first
+    pub addresses: Vec<UserAddress>,
+}
+
+#[derive(Debug)]
+pub struct Order {
+    pub id: u32,
+    /// What the customer saw on the invoice, not the id
+    pub number: String,
+    /// `None` for orders without an account
+    pub user_id: Option<u32>,
+    pub email: String,
+    pub status_id: u32,
+    pub note: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub company: Option<String>,
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub zip: Option<String>,
+    pub country_id: Option<String>,
+    pub phone: Option<String>,
+    pub delivery_first_name: Option<String>,
+    pub delivery_last_name: Option<String>,
+    pub delivery_company: Option<String>,
+    pub delivery_street: Option<String>,
+    pub delivery_city: Option<String>,
+    pub delivery_zip: Option<String>,
+    pub delivery_country_id: Option<String>,
+    pub delivery_phone: Option<String>,
+    pub shipping_name: Option<String>,
+    pub shipping_price: Option<BigDecimal>,
+    pub shipping_price_with_vat: Option<BigDecimal>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Prices are per piece, as they were at the time of the order
+#[derive(Debug)]
+pub struct OrderLine {
+    pub id: u32,
+    pub order_id: u32,
+    /// `None` or a deleted product for things sold before the product was removed
+    pub product_id: Option<u32>,
+    pub name: String,
+    pub code: Option<String>,
+    pub quantity: u32,
+    pub price: BigDecimal,
+    pub price_with_vat: BigDecimal,
+    /// In percent
+    pub vat: Option<BigDecimal>,
+}
+
+#[derive(Debug)]
+pub struct FinalOrder {
+    pub order: Order,
+    pub billing: UserAddress,
+    /// Only when it differs from the billing one
+    pub shipping: Option<UserAddress>,
+    /// Oldest first, as they were in the order
+    pub lines: Vec<OrderLine>,
+}
+
 pub struct SKU {
     pub base_sku: String,
     pub latest_sku_suffix: u16,
 }
 #[derive(Debug)]
 pub struct FinalCategory {
-    pub me: Weak<RefCell<Self>>,
-    pub category: Rc<RefCell<Category>>,
-    pub parent_category: Option<Rc<RefCell<FinalCategory>>>,
+    pub me: Weak<RwLock<Self>>,
+    pub category: Arc<RwLock<Category>>,
+    pub parent_category: Option<Arc<RwLock<FinalCategory>>>,
     pub saleor_id: Option<cynic::Id>,
     pub image: Option<String>,
-    pub product_type: Option<Rc<RefCell<FinalProductType>>>,
+    pub product_type: Option<Arc<RwLock<FinalProductType>>>,
+    /// Texts in the other languages from `languages`, pushed after the category exists
+    pub translations: Vec<Translation>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct FinalProductType {
     pub name: String,
     pub saleor_id: Option<cynic::Id>,
+    /// Attribute slugs, see `YamlCategories::atributy`
+    pub attributes: Vec<String>,
 }
 
 impl FinalCategory {
-    pub fn new(category: Category) -> Rc<RefCell<Self>> {
-        Rc::new_cyclic(|me| {
-            RefCell::new(FinalCategory {
+    pub fn new(category: Category) -> Arc<RwLock<Self>> {
+        Arc::new_cyclic(|me| {
+            RwLock::new(FinalCategory {
                 me: me.clone(),
-                category: Rc::new(RefCell::new(category)),
+                category: Arc::new(RwLock::new(category)),
                 parent_category: None,
                 saleor_id: None,
                 image: None,
                 product_type: None,
+                translations: Vec::new(),
             })
         })
     }
-    pub fn new_self_rc(&self) -> Rc<RefCell<Self>> {
+    pub fn new_self_rc(&self) -> Arc<RwLock<Self>> {
         self.me
             .upgrade()
             .context("Failed to upgrade pointer to FinalCategory")
@@ -402,33 +902,19 @@ impl FinalCategory {
     pub async fn from_categories(
         categories: Vec<Category>,
         files: Vec<File>,
-        product_types: &mut Vec<Rc<RefCell<FinalProductType>>>,
-    ) -> Vec<Rc<RefCell<Self>>> {
+        product_types: &mut Vec<Arc<RwLock<FinalProductType>>>,
+    ) -> Vec<Arc<RwLock<Self>>> {
         //filter out not our categorien
         //CHECK FOR DATE OF DELTETION: IF ANY, FILTER IT OUT
-        let mut final_categories: Vec<Rc<RefCell<FinalCategory>>> = categories
+        let mut final_categories: Vec<Arc<RwLock<FinalCategory>>> = categories
             .into_iter()
             .filter_map(|cat| {
                 if cat.name.to_lowercase().contains("test") || cat.deleted_at.is_some() {
                     return None;
                 }
-                //INFO: MAGIC NUMBER!
                 //Remove non-relevant categories
-                match cat.name.as_str() {
-                    "Root" => return None,
-                    "Vianočné dekorácie" => return None,
-                    "Veľkonočné dekorácie" => return None,
-                    "Roľničky kovové" => return None,
-                    "Dekorácia zápich" => return None,
-                    "Aplikácie so zapínaním" => return None,
-                    "Ozdoby sisalové" => return None,
-                    "Girlandy" => return None,
-                    "Ozdoby na zavesenie" => return None,
-                    "Dekoračné predmety" => return None,
-                    "Aplikácie s magnetom" => return None,
-                    "Aplikácie na drôtiku" => return None,
-                    "Kategórie" => return None,
-                    _ => {}
+                if config().excluded_categories.contains(&cat.name) {
+                    return None;
                 }
                 Some(FinalCategory::new(cat))
             })
@@ -438,13 +924,13 @@ impl FinalCategory {
         //fetch missing names and descriptions
         let pool = sqlx::mysql::MySqlPoolOptions::new()
             .max_connections(20)
-            .connect(gql_queries::SQL_Endpoint)
+            .connect(&config().sql_endpoint)
             .await
             .expect("Failed creating sql connection");
 
         for c in final_categories.iter_mut() {
-            let cat = c.borrow_mut();
-            let mut cat_cat = cat.category.borrow_mut();
+            let mut cat = c.write();
+            let mut cat_cat = cat.category.write();
 
             let id = &cat_cat.id.clone();
             let category_texts_q = sqlx::query_as!(
@@ -458,46 +944,51 @@ impl FinalCategory {
             let mut cat_texts = category_texts_q
                 .await
                 .expect("failed fetching category_texts");
+            // newest first, default language ones before the rest
+            cat_texts.sort_unstable_by(|t, n| n.updated_at.cmp(&t.updated_at));
+            cat_texts.sort_by_key(|t| t.language_id != config().default_language_id);
             if let Some(text) = cat_texts.get(0) {
                 cat_cat.description = text.description.clone();
             }
             if cat_cat.name.is_empty() {
-                cat_texts.sort_unstable_by(|t, n| n.updated_at.cmp(&t.updated_at));
                 if let Some(name) = cat_texts.get(0) {
                     cat_cat.name = name.name.clone()
                 };
             }
+            drop(cat_cat);
+            cat.translations =
+                translations(&cat_texts, |t| (t.language_id, &t.name, "", &t.description));
         }
 
         //filter out empty named categories(prolly duds)
-        let mut final_categories: Vec<Rc<RefCell<FinalCategory>>> = final_categories
+        let mut final_categories: Vec<Arc<RwLock<FinalCategory>>> = final_categories
             .into_iter()
-            .filter(|c| !c.borrow().category.borrow().name.is_empty())
+            .filter(|c| !c.read().category.read().name.is_empty())
             .collect();
 
-        // dbg!(final_categories[3].borrow().category.borrow());
+        // dbg!(final_categories[3].read().category.read());
 
         //pair categories to sub categories etc
         for category in final_categories.iter_mut() {
             let find_res = temp_final_categories.iter().find(|cat| {
-                cat.borrow().category.borrow().id
+                cat.read().category.read().id
                     == category
-                        .borrow()
+                        .read()
                         .category
-                        .borrow()
+                        .read()
                         .parent_id
                         .unwrap_or(u32::MAX)
             });
 
             if let Some(parent_category) = find_res {
-                category.borrow_mut().parent_category = Some(parent_category.clone());
+                category.write().parent_category = Some(parent_category.clone());
             }
 
             //assigns image filename
-            let mut cat = category.borrow_mut();
+            let mut cat = category.write();
             let mut image: Option<&File> = None;
             {
-                let mut cat_cat = cat.category.borrow_mut();
+                let mut cat_cat = cat.category.write();
                 cat_cat.description =
                     gql_queries::Jsonstring::parse_old_json(&cat_cat.description).to_string();
 
@@ -515,8 +1006,8 @@ impl FinalCategory {
         // Make an array of product_types, compare if new one is actually new,
         // either append new or replace with existing or smt...
         for cat in final_categories.iter_mut() {
-            let mut cat = cat.borrow_mut();
-            let id = cat.category.borrow().id;
+            let mut cat = cat.write();
+            let id = cat.category.read().id;
             cat.product_type = yaml_cat.find_product_type(id, product_types);
         }
 
@@ -596,6 +1087,8 @@ pub struct Product {
     pub unit_id: Option<u32>,
     pub discount: i32,
     pub status: String,
+    /// Not read, its format isn't documented anywhere. `products_texts` has the same texts per
+    /// language with dates to pick the newest by
     #[sqlx(default)]
     pub translation: Option<Vec<u8>>,
     pub availability_text_id: Option<i32>,
@@ -604,10 +1097,189 @@ pub struct Product {
     pub ebay: Option<i32>,
     pub mall: Option<i32>,
 }
-#[derive(sqlx::Type, Clone, Copy, Serialize, Deserialize, Debug)]
+#[derive(sqlx::Type, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
 #[sqlx(type_name = "status", rename_all = "lowercase")]
 pub enum Status {
     Available,
     Ended,
     Arrival,
 }
+
+impl Product {
+    /// `status` comes as a plain string, anything unknown counts as available
+    pub fn status(&self) -> Status {
+        match self.status.trim().to_lowercase().as_str() {
+            "ended" => Status::Ended,
+            "arrival" => Status::Arrival,
+            _ => Status::Available,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GroupingRule, Pattern};
+
+    fn category(id: u32) -> Arc<RwLock<FinalCategory>> {
+        let category = serde_json::json!({
+            "id": id,
+            "name": "Oblečenie",
+            "slug": "oblecenie",
+            "description": "",
+            "titleHelp": "",
+            "isRoot": 0,
+            "is_active": 1,
+            "sort_id": 0,
+        });
+        FinalCategory::new(serde_json::from_value(category).unwrap())
+    }
+
+    fn product(
+        id: u32,
+        name: &str,
+        code: &str,
+        category: &Arc<RwLock<FinalCategory>>,
+    ) -> FinalProduct {
+        FinalProduct {
+            product: Product {
+                id,
+                name: name.to_owned(),
+                short_description: String::new(),
+                description: String::new(),
+                image_id: None,
+                created_at: None,
+                updated_at: None,
+                deleted_at: None,
+                id_language: 1,
+                code: code.to_owned(),
+                wholesale_price: None,
+                retail_price: None,
+                wholesale_price_with_iva: None,
+                retail_price_with_iva: None,
+                quantity: None,
+                unit_id: None,
+                discount: 0,
+                status: String::new(),
+                translation: None,
+                availability_text_id: None,
+                weight: None,
+                amazon: None,
+                ebay: None,
+                mall: None,
+            },
+            saleor_id: None,
+            category: Some(category.clone()),
+            images: Vec::new(),
+            price: None,
+            prices: Vec::new(),
+            SKU: code.to_owned(),
+            slug: format!("product-{}", id),
+            old_slug: format!("product-{}", id),
+            siblings: Vec::new(),
+            variant_value: None,
+            attributes: Vec::new(),
+            attachments: Vec::new(),
+            translations: Vec::new(),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn grouping(rules: &[(GroupBy, &str)]) -> VariantGrouping {
+        VariantGrouping {
+            attribute_name: "Farba".to_owned(),
+            attribute_slug: "farba".to_owned(),
+            rules: rules
+                .iter()
+                .map(|(by, pattern)| GroupingRule {
+                    by: *by,
+                    pattern: Pattern(regex::Regex::new(pattern).unwrap()),
+                })
+                .collect(),
+        }
+    }
+
+    fn slugify() -> regex::Regex {
+        regex::Regex::new(r"[^a-zA-Z0-9-]+").unwrap()
+    }
+
+    fn summary(products: &[FinalProduct]) -> Vec<(u32, Option<&str>, Vec<u32>)> {
+        products
+            .iter()
+            .map(|p| {
+                let siblings = p.siblings.iter().map(|s| s.product.id).collect();
+                (p.product.id, p.variant_value.as_deref(), siblings)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn without_grouping_nothing_changes() {
+        let shirts = category(1);
+        let products = vec![
+            product(1, "Tričko - červené", "T-1", &shirts),
+            product(2, "Tričko - modré", "T-2", &shirts),
+        ];
+        let grouped = FinalProduct::group_variants(products, None, &slugify());
+        assert_eq!(summary(&grouped), [(1, None, vec![]), (2, None, vec![])]);
+    }
+
+    #[test]
+    fn siblings_by_name_share_the_family_name() {
+        let shirts = category(1);
+        let hats = category(2);
+        let products = vec![
+            product(1, "Tričko Basic - červené", "T-1", &shirts),
+            product(2, "Mikina", "M-1", &shirts),
+            product(3, "Tričko Basic - modré", "T-2", &shirts),
+            // same base, different category, a family of its own
+            product(4, "Tričko Basic - zelené", "T-3", &hats),
+            product(5, "Tričko Basic - biele", "T-4", &shirts),
+        ];
+        let grouping = grouping(&[(GroupBy::Name, r"^(?P<base>.+) - (?P<variant>[^-]+)$")]);
+        let grouped = FinalProduct::group_variants(products, Some(&grouping), &slugify());
+        assert_eq!(
+            summary(&grouped),
+            [
+                (1, Some("červené"), vec![3, 5]),
+                (2, None, vec![]),
+                (4, None, vec![]),
+            ]
+        );
+        assert_eq!(grouped[0].product.name, "Tričko Basic");
+        assert_eq!(grouped[0].slug, "tricko-basic");
+        assert_eq!(grouped[0].old_slug, "product-1");
+        assert_eq!(
+            grouped[0].siblings[0].variant_value.as_deref(),
+            Some("modré")
+        );
+        // a family of one keeps its own name
+        assert_eq!(grouped[2].product.name, "Tričko Basic - zelené");
+        assert_eq!(grouped[2].slug, "product-4");
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_sku_families_keep_names() {
+        let shirts = category(1);
+        let products = vec![
+            product(1, "Tričko červené", "TR-100-RED", &shirts),
+            product(2, "Tričko modré", "TR-100-BLUE", &shirts),
+            product(3, "Čiapka - sivá", "CAP", &shirts),
+            product(4, "Čiapka - čierna", "CAP2", &shirts),
+        ];
+        let grouping = grouping(&[
+            (GroupBy::Sku, r"^(?P<base>[A-Z]+-\d+)-(?P<variant>[A-Z]+)$"),
+            (GroupBy::Name, r"^(?P<base>.+) - (?P<variant>.+)$"),
+        ]);
+        let grouped = FinalProduct::group_variants(products, Some(&grouping), &slugify());
+        assert_eq!(
+            summary(&grouped),
+            [(1, Some("RED"), vec![2]), (3, Some("sivá"), vec![4])]
+        );
+        assert_eq!(grouped[0].product.name, "Tričko červené");
+        assert_eq!(grouped[0].slug, "product-1");
+        assert_eq!(grouped[1].product.name, "Čiapka");
+        assert_eq!(grouped[1].slug, "ciapka");
+    }
+}
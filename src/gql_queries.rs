@@ -1,27 +1,31 @@
 #![allow(non_upper_case_globals, non_camel_case_types, non_snake_case)]
 
-use std::{cell::RefCell, rc::Rc, str::FromStr};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
 
-use cynic::{http::SurfExt, GraphQlResponse, Id, MutationBuilder};
-use dotenvy_macro::dotenv;
+use anyhow::Context;
+use cynic::{Id, MutationBuilder, QueryBuilder};
+use parking_lot::RwLock;
 use rust_decimal::Decimal;
+use sqlx::types::BigDecimal;
 
 use crate::{
-    get_sqls::{FinalProduct, FinalProductType, OldJson},
-    saleor_login,
+    config::{config, EndedProducts, MediaUpload, OrdersConfig},
+    editorjs,
+    get_sqls::{
+        FinalCustomer, FinalProduct, FinalProductType, OldJson, OrderLine, Status, Translation,
+        UserAddress,
+    },
+    media_server,
+    orders::PreparedOrder,
+    saleor::{FileUpload, MutationError, MutationErrors, Saleor, SaleorError},
 };
-use serde::de::IgnoredAny;
-use surf::Client;
 
 use self::schema::__fields::ProductMedia;
 
-pub const GQL_Endpoint: &str = dotenv!("GRAPHQL_URL");
-pub const SQL_Endpoint: &str = dotenv!("DATABASE_URL");
-// All producst are in this app assigned to a single warehouse and tax class and channel
-pub const Product_Channel_ID: &str = "";
-pub const Porudct_Tax_Class_ID: &str = "";
-pub const Product_Warehouse_ID: &str = "";
-
 #[cynic::schema("saleor")]
 mod schema {}
 
@@ -55,6 +59,25 @@ pub struct AccountError {
     pub message: Option<String>,
 }
 
+#[derive(cynic::QueryVariables, Debug)]
+pub struct RefreshTokenVariables<'a> {
+    pub refresh_token: &'a str,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "RefreshTokenVariables")]
+pub struct RefreshToken {
+    #[arguments(refreshToken: $refresh_token)]
+    pub token_refresh: Option<RefreshToken2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "RefreshToken")]
+pub struct RefreshToken2 {
+    pub token: Option<String>,
+    pub errors: Vec<AccountError>,
+}
+
 /*
     ----------------- CREATE CATEGORY -------------------
 */
@@ -80,6 +103,8 @@ pub struct CategoryCreate {
 
 #[derive(cynic::QueryFragment, Debug)]
 pub struct ProductError {
+    pub field: Option<String>,
+    pub message: Option<String>,
     pub code: ProductErrorCode,
 }
 
@@ -121,7 +146,7 @@ pub struct CategoryInput<'a> {
     #[cynic(skip_serializing_if = "Option::is_none")]
     pub slug: Option<&'a str>,
     #[cynic(skip_serializing_if = "Option::is_none")]
-    pub seo: Option<SeoInput<'a>>,
+    pub seo: Option<SeoInput>,
     #[cynic(skip_serializing_if = "Option::is_none")]
     pub background_image: Option<Upload>,
     #[cynic(skip_serializing_if = "Option::is_none")]
@@ -139,9 +164,9 @@ pub struct MetadataInput<'a> {
 }
 
 #[derive(cynic::InputObject, Debug)]
-pub struct SeoInput<'a> {
-    pub title: Option<&'a str>,
-    pub description: Option<&'a str>,
+pub struct SeoInput {
+    pub title: Option<String>,
+    pub description: Option<String>,
 }
 #[derive(cynic::Scalar, Debug, Clone)]
 pub struct Upload(pub String);
@@ -157,27 +182,10 @@ impl std::fmt::Display for Jsonstring {
 }
 
 impl Jsonstring {
+    /// Old html description as Editor.js blocks, see `editorjs::from_html`
     pub fn from_string(text: String) -> Self {
-        use rand::distributions::{Alphanumeric, DistString};
-        use serde_json::json;
-        let id = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
-        let dt = chrono::offset::Local::now().timestamp();
-        let json = json!(
-        {
-            "time": dt,
-            "blocks": [
-                {
-                "id": id,
-                "type": "paragraph",
-                "data": {
-                    "text": text
-                }
-                }
-            ],
-            "version": "2.24.3"
-        }
-                );
-        Jsonstring(json.to_string())
+        let time = chrono::offset::Local::now().timestamp();
+        Jsonstring(editorjs::from_html(&text, time, editorjs::random_id).to_string())
     }
     pub fn purify_old_json(text: &String) -> String {
         let mut new_text = text.to_owned();
@@ -225,8 +233,8 @@ impl Jsonstring {
             }
         }
         */
-        //un-escape html symbols
-        new_text = html_escape::decode_html_entities(&new_text).to_string();
+        // Entities stay as they are, decoded here escaped text would turn into live markup. The
+        // short description decodes them itself, it's only ever metadata
         new_text
     }
 
@@ -235,6 +243,108 @@ impl Jsonstring {
     }
 }
 
+pub fn category_input<'a>(
+    category: &'a crate::get_sqls::Category,
+    old_id: &'a str,
+) -> CategoryInput<'a> {
+    let mut metadata = vec![MetadataInput {
+        //INFO: MAGIC NUMBER!
+        key: "old_id",
+        value: old_id,
+    }];
+    // Saleor's seo has no keywords, they're kept around for whoever still wants them
+    if let Some(keywords) = category
+        .keywords
+        .as_deref()
+        .filter(|k| !k.trim().is_empty())
+    {
+        metadata.push(MetadataInput {
+            key: "keywords",
+            value: keywords.trim(),
+        });
+    }
+    CategoryInput {
+        description: Some(Jsonstring(category.description.to_owned())),
+        name: Some(category.name.as_str()),
+        slug: Some(category.slug.as_str()),
+        seo: Some(category_seo(category)),
+        background_image: None,
+        background_image_alt: Some(category.name.as_str()),
+        metadata: Some(metadata),
+        private_metadata: None,
+    }
+}
+
+/// Saleor refuses longer ones
+const SEO_TITLE_MAX: usize = 70;
+const SEO_DESCRIPTION_MAX: usize = 300;
+
+/// The old meta title and description, or the name and description where those are empty
+pub fn category_seo(category: &crate::get_sqls::Category) -> SeoInput {
+    let description = editorjs::text(&category.description);
+    SeoInput {
+        title: seo_text(
+            &[category.meta_title.as_deref(), Some(category.name.as_str())],
+            SEO_TITLE_MAX,
+        ),
+        description: seo_text(
+            &[category.meta_description.as_deref(), description.as_deref()],
+            SEO_DESCRIPTION_MAX,
+        ),
+    }
+}
+
+/// Products never had meta fields, the name and short (or long) description stand in
+pub fn product_seo(product: &crate::get_sqls::Product) -> SeoInput {
+    SeoInput {
+        title: seo_text(&[Some(product.name.as_str())], SEO_TITLE_MAX),
+        description: seo_text(
+            &[
+                Some(product.short_description.as_str()),
+                Some(product.description.as_str()),
+            ],
+            SEO_DESCRIPTION_MAX,
+        ),
+    }
+}
+
+/// First candidate with any text left once the markup is gone, cut to `max` characters
+fn seo_text(candidates: &[Option<&str>], max: usize) -> Option<String> {
+    candidates
+        .iter()
+        .flatten()
+        .map(|text| plain_text(text))
+        .find(|text| !text.is_empty())
+        .map(|text| truncate(text, max))
+}
+
+/// Old texts are html, sometimes still wrapped in the old translation json
+pub fn plain_text(text: &str) -> String {
+    static TAGS: OnceLock<regex::Regex> = OnceLock::new();
+    let tags = TAGS.get_or_init(|| regex::Regex::new(r"<[^>]*>").unwrap());
+    let text = Jsonstring::purify_old_json(&text.to_owned());
+    let text = tags.replace_all(&text, " ");
+    let text = html_escape::decode_html_entities(&text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cuts on the last word boundary that fits and marks the cut with an ellipsis
+fn truncate(text: String, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text;
+    }
+    // Not even the ellipsis fits
+    if max == 0 {
+        return String::new();
+    }
+    let cut: String = text.chars().take(max - 1).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches([',', '.', ';', ':', '-', ' ']))
+}
+
 /*
     ----------------- CREATE PRODUDCT TYPES ------------
 */
@@ -294,86 +404,50 @@ pub struct ProductTypeInput<'a> {
     pub tax_class: Option<&'a cynic::Id>,
 }
 
-pub async fn create_product_type(
-    typ: Rc<RefCell<FinalProductType>>,
-    tax_class_id: &cynic::Id,
-    client: &mut Client,
-    jwt: &String,
-) -> Result<GraphQlResponse<CreateProductType, IgnoredAny>, SaleorGraphqlError> {
-    let typ = typ.borrow();
-    let slug = &typ.name.to_lowercase().replace(" ", "-");
-    let create_product_type_operation = CreateProductType::build(CreateProductTypeVariables {
+pub fn product_type_create_operation<'a>(
+    typ: &'a FinalProductType,
+    slug: &'a str,
+    tax_class_id: &'a cynic::Id,
+    attributes: &AttributeIds,
+) -> cynic::Operation<CreateProductType, CreateProductTypeVariables<'a>> {
+    CreateProductType::build(CreateProductTypeVariables {
         input: ProductTypeInput {
             is_digital: Some(false),
-            tax_class: Some(&tax_class_id),
+            tax_class: Some(tax_class_id),
             tax_code: None,
             slug: Some(slug),
             name: Some(&typ.name),
             kind: Some(ProductTypeKindEnum::Normal),
-            has_variants: None,
+            has_variants: Some(attributes.variant.is_some()),
+            //INFO: MAGIC NUMBER!
             weight: Some(WeightScalar("0.5".to_owned())),
             product_attributes: None,
             variant_attributes: None,
             is_shipping_required: Some(true),
         },
-    });
-
-    let create_product_type_response = client
-        .post(GQL_Endpoint)
-        .header("Authorization", jwt)
-        .run_graphql(create_product_type_operation)
-        .await;
-
-    if let Ok(create_product_type_response) = create_product_type_response {
-        let create_product_type_operation = CreateProductType::build(CreateProductTypeVariables {
-            input: ProductTypeInput {
-                is_digital: Some(false),
-                tax_class: Some(&tax_class_id),
-                tax_code: None,
-                slug: Some(&slug),
-                name: Some(&typ.name),
-                kind: Some(ProductTypeKindEnum::Normal),
-                has_variants: None,
-                weight: Some(WeightScalar("0.5".to_owned())),
-                product_attributes: None,
-                variant_attributes: None,
-                is_shipping_required: Some(true),
-            },
-        });
-
-        if create_product_type_response.errors.is_some()
-            || create_product_type_response.data.as_ref().is_some_and(|x| {
-                x.product_type_create
-                    .as_ref()
-                    .is_some_and(|y| y.errors.len() > 0)
-            })
-        {
-            println!("{:?}", &create_product_type_operation.query);
-            println!("{:?}", &create_product_type_operation.variables);
-            println!("{:?}", &create_product_type_response);
-        }
+    })
+}
 
-        if let Some(data) = &create_product_type_response.data {
-            if let Some(create) = &data.product_type_create {
-                for err in &create.errors {
-                    println!("{:?}", err);
-                    return Err(SaleorGraphqlError::Other(err.code));
-                }
-            }
-        }
+pub async fn create_product_type(
+    typ: Arc<RwLock<FinalProductType>>,
+    tax_class_id: &cynic::Id,
+    attributes: &AttributeIds,
+    saleor: &Saleor,
+) -> Result<cynic::Id, SaleorError<ProductErrorCode>> {
+    let typ = typ.read();
+    let slug = &typ.name.to_lowercase().replace(" ", "-");
+    let create_product_type_operation =
+        product_type_create_operation(&typ, slug, tax_class_id, attributes);
 
-        if let Some(data) = &create_product_type_response.errors {
-            for dat in data {
-                if dat.message == "Signature has expired" {
-                    return Err(SaleorGraphqlError::SignatureExpired);
-                }
-                println!("dat");
-                return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-            }
-        }
-        return Ok(create_product_type_response);
-    }
-    return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
+    let product_type = saleor
+        .mutate(create_product_type_operation)
+        .await?
+        .product_type_create
+        .and_then(|c| c.product_type)
+        .ok_or(SaleorError::NoData)?;
+    // The attributes go on with `assign_attributes`, recorded on their own so a failure there
+    // doesn't lose the type
+    Ok(product_type.id)
 }
 
 /*
@@ -433,6 +507,7 @@ pub struct ProductVariantChannelListingUpdate {
 #[derive(cynic::QueryFragment, Debug)]
 pub struct ProductVariant {
     pub id: cynic::Id,
+    pub sku: Option<String>,
 }
 
 #[derive(cynic::QueryFragment, Debug)]
@@ -538,7 +613,7 @@ pub struct ProductCreateInput<'a> {
     #[cynic(skip_serializing_if = "Option::is_none")]
     pub tax_code: Option<&'a str>,
     #[cynic(skip_serializing_if = "Option::is_none")]
-    pub seo: Option<SeoInput<'a>>,
+    pub seo: Option<SeoInput>,
     #[cynic(skip_serializing_if = "Option::is_none")]
     pub weight: Option<WeightScalar>,
     #[cynic(skip_serializing_if = "Option::is_none")]
@@ -589,22 +664,37 @@ pub struct PreorderSettingsInput {
     pub end_date: Option<DateTime>,
 }
 
-#[derive(cynic::InputObject, Debug)]
+#[derive(cynic::InputObject, Default, Debug)]
 pub struct AttributeValueInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub id: Option<&'a cynic::Id>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub external_reference: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub values: Option<Vec<&'a str>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub dropdown: Option<AttributeValueSelectableTypeInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub swatch: Option<AttributeValueSelectableTypeInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub multiselect: Option<Vec<AttributeValueSelectableTypeInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub numeric: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub file: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub content_type: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub references: Option<Vec<&'a cynic::Id>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub rich_text: Option<Jsonstring>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub plain_text: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub boolean: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub date: Option<Date>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
     pub date_time: Option<DateTime>,
 }
 
@@ -660,12 +750,6 @@ pub struct ProductMediaCreateInput<'a> {
     pub media_url: Option<String>,
 }
 
-#[derive(PartialEq, Eq, Debug)]
-pub enum SaleorGraphqlError {
-    SignatureExpired,
-    Other(ProductErrorCode),
-}
-
 /* --- ASSING MEDIA TO VARIANTS --- */
 
 #[derive(cynic::QueryVariables, Debug)]
@@ -687,529 +771,3979 @@ pub struct VariantMediaAssign2 {
     pub errors: Vec<ProductError>,
 }
 
-/* --- ACTUAL QUERIES--- */
+/* --- EXISTING OBJECTS, MATCHED BY OLD_ID METADATA --- */
 
-pub async fn create_product(
-    category_id: Option<&Id>,
-    product: &mut FinalProduct,
-    tax_class_id: &Id,
-    default_product_type_id: &Id,
-    old_id: u32,
-    client: &mut Client,
-    jwt: &mut String,
-) -> Result<GraphQlResponse<ProductCreate, IgnoredAny>, SaleorGraphqlError> {
-    //If product type has saleor_id, use that, else create product type and assign that, else use
-    //default product type id
-    let mut product_type_id: cynic::Id = default_product_type_id.clone();
-    if let Some(category) = &product.category {
-        if let Some(product_type) = &category.borrow().product_type {
-            let saleor_id = product_type.borrow().saleor_id.clone();
-            if let Some(prod_type_saleor_id) = saleor_id {
-                product_type_id = prod_type_saleor_id.clone();
-            } else {
-                println!("creating product type {}", &product_type.borrow().name);
-                'a: loop {
-                    let create_product_type_result =
-                        create_product_type(product_type.clone(), tax_class_id, client, jwt).await;
-                    match create_product_type_result {
-                        Err(e) => match e {
-                            SaleorGraphqlError::Other(ee) => {
-                                println!(
-                                    "create product type '{}' failed, code: {:?}",
-                                    product_type.borrow().name,
-                                    ee
-                                );
-                                break 'a;
-                            }
-                            SaleorGraphqlError::SignatureExpired => {
-                                let (new_client, new_jwt) = saleor_login()
-                                    .await
-                                    .expect("failed to create product type during product cuz prolly signature");
-                                *jwt = new_jwt;
-                                *client = new_client;
-                            }
-                        },
-                        Ok(data) => {
-                            if let Some(data) = data.data {
-                                if let Some(product_type_create) = data.product_type_create {
-                                    if let Some(prd) = product_type_create.product_type {
-                                        product_type.borrow_mut().saleor_id = Some(prd.id.clone());
-                                        product_type_id = prd.id;
-                                        println!("success!");
-                                    }
-                                }
-                            }
-                            break 'a;
-                        }
-                    }
-                }
-            }
-        }
-    }
-    let description = Jsonstring::from_string(product.product.description.clone());
-    let weight = product.product.weight.map(|w| WeightScalar(w.to_string()));
-    let old_id = old_id.to_string();
-    let create_product_operation = ProductCreate::build(ProductCreateVariables {
-        input: ProductCreateInput {
-            attributes: None,
-            category: category_id,
-            charge_taxes: Some(true),
-            collections: None,
-            description: Some(description.clone()),
-            name: Some(product.product.name.as_str()),
-            slug: Some(product.slug.as_str()),
-            tax_class: Some(tax_class_id),
-            tax_code: None,
-            seo: None,
-            weight: weight.clone(),
-            rating: None,
-            metadata: Some(vec![
-                MetadataInput {
-                    key: "short_description",
-                    value: product.product.short_description.as_str(),
-                },
-                MetadataInput {
-                    key: "old_id",
-                    value: &old_id,
-                },
-            ]),
-            private_metadata: None,
-            external_reference: None,
-            product_type: &product_type_id,
-        },
-    });
+#[derive(cynic::QueryVariables, Debug)]
+pub struct ExistingObjectsVariables<'a> {
+    pub after: Option<&'a str>,
+}
 
-    let create_product_response = client
-        .post(GQL_Endpoint)
-        .header("Authorization", jwt.clone())
-        .run_graphql(create_product_operation)
-        .await;
-
-    if let Ok(create_product_response) = create_product_response {
-        //Wish I didn't have to do this tho
-        let create_product_operation = ProductCreate::build(ProductCreateVariables {
-            input: ProductCreateInput {
-                attributes: None,
-                category: category_id,
-                charge_taxes: Some(true),
-                collections: None,
-                description: Some(description),
-                name: Some(product.product.name.as_str()),
-                slug: Some(product.slug.as_str()),
-                tax_class: Some(tax_class_id),
-                tax_code: None,
-                seo: None,
-                weight,
-                rating: None,
-                metadata: None,
-                private_metadata: None,
-                external_reference: None,
-                product_type: &product_type_id,
-            },
-        });
-        if create_product_response.errors.is_some()
-            || create_product_response.data.as_ref().is_some_and(|x| {
-                x.product_create
-                    .as_ref()
-                    .is_some_and(|y| y.errors.len() > 0)
-            })
-        {
-            println!("{:?}", &create_product_operation.query);
-            println!("{:?}", &create_product_operation.variables);
-            println!("{:?}", &create_product_response);
-        }
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "ExistingObjectsVariables")]
+pub struct ExistingCategories {
+    #[arguments(first: 100, after: $after, filter: { metadata: [{ key: "old_id" }] })]
+    pub categories: Option<CategoryCountableConnection>,
+}
 
-        if let Some(data) = &create_product_response.data {
-            if let Some(create) = &data.product_create {
-                for err in &create.errors {
-                    println!("{:?}", err);
-                    return Err(SaleorGraphqlError::Other(err.code));
-                }
-            }
-        }
+#[derive(cynic::QueryFragment, Debug)]
+pub struct CategoryCountableConnection {
+    pub page_info: PageInfo,
+    pub edges: Vec<CategoryCountableEdge>,
+}
 
-        if let Some(data) = &create_product_response.errors {
-            for dat in data {
-                if dat.message == "Signature has expired" {
-                    return Err(SaleorGraphqlError::SignatureExpired);
-                }
-                println!("dat");
-                return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-            }
-        }
-        return Ok(create_product_response);
-    }
-    return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
+#[derive(cynic::QueryFragment, Debug)]
+pub struct CategoryCountableEdge {
+    pub node: ExistingCategory,
 }
 
-pub async fn product_channel_listing_update(
-    product: &mut FinalProduct,
-    client: &mut Client,
-    jwt: &String,
-    channel_id: &Id,
-) -> Result<GraphQlResponse<ProductChannelListingUpdate>, SaleorGraphqlError> {
-    if let Some(product_saleor_id) = &product.saleor_id {
-        //4.1 productChannelListingUpdate
-        // product
-        //                             .product
-        //                             .created_at
-        // .map(|d| gql_queries::Date(d.to_rfc3339()))
-
-        let channel_listing_update_operation =
-            ProductChannelListingUpdate::build(ProductChannelListingUpdateVariables {
-                id: product_saleor_id,
-                input: ProductChannelListingUpdateInput {
-                    update_channels: Some(vec![ProductChannelListingAddInput {
-                        add_variants: None,
-                        remove_variants: None,
-                        available_for_purchase_at: None,
-                        published_at: None,
-                        available_for_purchase_date: None,
-                        publication_date: None,
-                        channel_id,
-                        is_available_for_purchase: Some(true),
-                        is_published: Some(true),
-                        visible_in_listings: Some(true),
-                    }]),
-                    remove_channels: None,
-                },
-            });
-
-        let channel_listing_update_response = client
-            .post(GQL_Endpoint)
-            .header("Authorization", jwt)
-            .run_graphql(channel_listing_update_operation)
-            .await;
-        if let Ok(channel_listing_update_response) = channel_listing_update_response {
-            let channel_listing_update_operation =
-                ProductChannelListingUpdate::build(ProductChannelListingUpdateVariables {
-                    id: product_saleor_id,
-                    input: ProductChannelListingUpdateInput {
-                        update_channels: Some(vec![ProductChannelListingAddInput {
-                            add_variants: None,
-                            remove_variants: None,
-                            available_for_purchase_at: None,
-                            published_at: None,
-                            available_for_purchase_date: None,
-                            publication_date: None,
-                            channel_id,
-                            is_available_for_purchase: Some(true),
-                            is_published: Some(true),
-                            visible_in_listings: Some(true),
-                        }]),
-                        remove_channels: None,
-                    },
-                });
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Category")]
+pub struct ExistingCategory {
+    pub id: cynic::Id,
+    /// What links lead to, updates keep it
+    pub slug: String,
+    #[arguments(key: "old_id")]
+    pub metafield: Option<String>,
+}
 
-            if channel_listing_update_response.errors.is_some()
-                || channel_listing_update_response
-                    .data
-                    .as_ref()
-                    .is_some_and(|x| {
-                        x.product_channel_listing_update
-                            .as_ref()
-                            .is_some_and(|y| y.errors.len() > 0)
-                    })
-            {
-                println!("{:?}", &channel_listing_update_operation.query);
-                println!("{:?}", &channel_listing_update_operation.variables);
-                println!("{:?}", &channel_listing_update_response);
-            }
-            if let Some(data) = &channel_listing_update_response.data {
-                if let Some(data) = &data.product_channel_listing_update {
-                    for err in &data.errors {
-                        println!("{:?}", err);
-                        return Err(SaleorGraphqlError::Other(err.code));
-                    }
-                }
-            }
-            if let Some(data) = &channel_listing_update_response.errors {
-                for dat in data {
-                    if dat.message == "Signature has expired" {
-                        return Err(SaleorGraphqlError::SignatureExpired);
-                    }
-                    println!("dat");
-                    return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-                }
-            }
-            Ok(channel_listing_update_response)
-        } else {
-            Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError))
-        }
-    } else {
-        Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError))
-    }
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "ExistingObjectsVariables")]
+pub struct ExistingProducts {
+    #[arguments(first: 100, after: $after, filter: { metadata: [{ key: "old_id" }] })]
+    pub products: Option<ProductCountableConnection>,
 }
 
-pub async fn variant_create(
-    product: &mut FinalProduct,
-    client: &mut Client,
-    jwt: &String,
-    warehouse_id: &Id,
-) -> Result<GraphQlResponse<VariantCreate>, SaleorGraphqlError> {
-    if let Some(saleor_product_id) = &product.saleor_id {
-        let mut stocks: Option<Vec<StockInput>> = None;
-        if let Some(q) = product.product.quantity {
-            stocks = Some(vec![StockInput {
-                warehouse: warehouse_id,
-                quantity: q,
-            }]);
-        };
-        let variant_create_operation = VariantCreate::build(VariantCreateVariables {
-            input: ProductVariantCreateInput {
-                product: saleor_product_id,
-                sku: Some(&product.SKU),
-                external_reference: None,
-                name: None,
-                attributes: Vec::new(),
-                metadata: None,
-                private_metadata: None,
-                preorder: None,
-                quantity_limit_per_customer: None,
-                stocks,
-                track_inventory: Some(true),
-                weight: None,
-            },
-        });
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ProductCountableConnection {
+    pub page_info: PageInfo,
+    pub edges: Vec<ProductCountableEdge>,
+}
 
-        let variant_create_response = client
-            .post(GQL_Endpoint)
-            .header("Authorization", jwt)
-            .run_graphql(variant_create_operation)
-            .await;
-
-        if let Ok(variant_create_response) = variant_create_response {
-            let variant_create_operation = VariantCreate::build(VariantCreateVariables {
-                input: ProductVariantCreateInput {
-                    product: saleor_product_id,
-                    sku: Some(product.product.code.as_str()),
-                    external_reference: None,
-                    name: None,
-                    attributes: Vec::new(),
-                    metadata: None,
-                    private_metadata: None,
-                    preorder: None,
-                    quantity_limit_per_customer: None,
-                    stocks: None,
-                    track_inventory: Some(true),
-                    weight: None,
-                },
-            });
-
-            if variant_create_response.errors.is_some()
-                || variant_create_response.data.as_ref().is_some_and(|x| {
-                    x.product_variant_create
-                        .as_ref()
-                        .is_some_and(|y| y.errors.len() > 0)
-                })
-            {
-                println!("{:?}", &variant_create_operation.query);
-                println!("{:?}", &variant_create_operation.variables);
-                println!("{:?}", &variant_create_response);
-            }
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ProductCountableEdge {
+    pub node: ExistingProduct,
+}
 
-            if let Some(data) = &variant_create_response.data {
-                if let Some(data) = &data.product_variant_create {
-                    for err in &data.errors {
-                        println!("{:?}", err);
-                        return Err(SaleorGraphqlError::Other(err.code));
-                    }
-                }
-            }
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Product")]
+pub struct ExistingProduct {
+    pub id: cynic::Id,
+    /// What links lead to, updates keep it
+    pub slug: String,
+    #[arguments(key: "old_id")]
+    pub metafield: Option<String>,
+    pub variants: Option<Vec<ProductVariant>>,
+    pub media: Option<Vec<ProductMedia2>>,
+}
 
-            if let Some(data) = &variant_create_response.errors {
-                for dat in data {
-                    if dat.message == "Signature has expired" {
-                        return Err(SaleorGraphqlError::SignatureExpired);
-                    }
-                    println!("dat");
-                    return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-                }
-            }
-            return Ok(variant_create_response);
-        }
-        return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-    }
-    Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError))
+#[derive(cynic::QueryFragment, Debug)]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
 }
 
-pub async fn variant_listing_update(
-    product: &mut FinalProduct,
-    variant_id: &Id,
-    channel_id: &Id,
-    client: &mut Client,
-    jwt: &String,
-) -> Result<GraphQlResponse<VariantChannelListingUpdate>, SaleorGraphqlError> {
-    if let Some(price) = product.price.as_ref().map(|price| {
-        PositiveDecimal(
-            Decimal::from_str(&price)
-                .unwrap_or(Decimal::new(0, 2))
-                .round_dp(2),
-        )
-    }) {
-        let variant_listing_update_operation =
-            VariantChannelListingUpdate::build(VariantChannelListingUpdateVariables {
-                id: variant_id,
-                input: vec![ProductVariantChannelListingAddInput {
-                    channel_id,
-                    cost_price: None,
-                    preorder_threshold: None,
-                    price: price.clone(),
-                }],
-            });
-
-        let variant_listing_update_response = client
-            .post(GQL_Endpoint)
-            .header("Authorization", jwt)
-            .run_graphql(variant_listing_update_operation)
-            .await;
-        if let Ok(variant_listing_update_response) = variant_listing_update_response {
-            let variant_listing_update_operation =
-                VariantChannelListingUpdate::build(VariantChannelListingUpdateVariables {
-                    id: variant_id,
-                    input: vec![ProductVariantChannelListingAddInput {
-                        channel_id,
-                        cost_price: None,
-                        preorder_threshold: None,
-                        price,
-                    }],
-                });
+/* --- UPDATE EXISTING CATEGORIES AND PRODUCTS --- */
 
-            if variant_listing_update_response.errors.is_some()
-                || variant_listing_update_response
-                    .data
-                    .as_ref()
-                    .is_some_and(|x| {
-                        x.product_variant_channel_listing_update
-                            .as_ref()
-                            .is_some_and(|y| y.errors.len() > 0)
-                    })
-            {
-                println!("{:?}", &variant_listing_update_operation.query);
-                println!("{:?}", &variant_listing_update_operation.variables);
-                println!("{:?}", &variant_listing_update_response);
-            }
+#[derive(cynic::QueryVariables, Debug)]
+pub struct UpdateCategoryVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: CategoryInput<'a>,
+}
 
-            if let Some(data) = &variant_listing_update_response.errors {
-                for dat in data {
-                    if dat.message == "Signature has expired" {
-                        return Err(SaleorGraphqlError::SignatureExpired);
-                    }
-                    println!("dat");
-                    return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-                }
-            }
-            return Ok(variant_listing_update_response);
-        }
-        return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-    }
-    Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError))
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "UpdateCategoryVariables")]
+pub struct UpdateCategory {
+    #[arguments(id: $id, input: $input)]
+    pub category_update: Option<CategoryUpdate>,
 }
 
-pub async fn product_media_create(
-    product: &mut FinalProduct,
-    client: &mut Client,
-    jwt: &mut String,
-) -> Result<Vec<GraphQlResponse<ProductMediaCreate>>, SaleorGraphqlError> {
-    if let Some(saleor_product_id) = &product.saleor_id {
-        let mut media_create_operations = vec![];
-        for image in product.images.iter() {
-            media_create_operations.push(ProductMediaCreate::build(ProductMediaCreateVariables {
-                input: ProductMediaCreateInput {
-                    alt: Some(product.product.name.as_str()),
-                    image: None,
-                    product: saleor_product_id,
-                    media_url: Some(image.clone()),
-                },
-            }));
-        }
-        let mut responses = vec![];
-        for media_create_operation in media_create_operations {
-            let media_create_response = client
-                .post(GQL_Endpoint)
-                .header("Authorization", &*jwt)
-                .run_graphql(media_create_operation)
-                .await;
-            if let Ok(media_create_response) = media_create_response {
-                if media_create_response.errors.is_some()
-                    || media_create_response.data.as_ref().is_some_and(|x| {
-                        x.product_media_create
-                            .as_ref()
-                            .is_some_and(|y| y.errors.len() > 0)
-                    })
-                {
-                    println!("{:?}", &media_create_response);
-                }
+#[derive(cynic::QueryFragment, Debug)]
+pub struct CategoryUpdate {
+    pub errors: Vec<ProductError>,
+    pub category: Option<Category>,
+}
 
-                if let Some(data) = &media_create_response.errors {
-                    for dat in data {
-                        if dat.message == "Signature has expired" {
-                            if let Ok((new_client, new_jwt)) = saleor_login().await {
-                                *jwt = new_jwt;
-                                *client = new_client;
-                            } else {
-                                return Err(SaleorGraphqlError::Other(ProductErrorCode::Invalid));
-                            }
-                        } else {
-                            return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-                        }
-                    }
-                }
-                responses.push(media_create_response);
-            }
-        }
-        Ok(responses)
-    } else {
-        Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError))
-    }
+#[derive(cynic::QueryVariables, Debug)]
+pub struct ProductUpdateVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: ProductInput<'a>,
 }
 
-pub async fn variant_media_assign(
-    variant_id: &Id,
-    media_id: &Id,
-    client: &mut Client,
-    jwt: &String,
-) -> Result<GraphQlResponse<VariantMediaAssign>, SaleorGraphqlError> {
-    let variant_media_assign_operation = VariantMediaAssign::build(VariantMediaAssignVariables {
-        variant_id,
-        media_id,
-    });
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "ProductUpdateVariables")]
+pub struct ProductUpdate {
+    #[arguments(id: $id, input: $input)]
+    pub product_update: Option<ProductUpdate2>,
+}
 
-    let variant_media_assign_result = client
-        .post(GQL_Endpoint)
-        .header("Authorization", jwt)
-        .run_graphql(variant_media_assign_operation)
-        .await;
-
-    if let Ok(variant_media_assign_result) = variant_media_assign_result {
-        let variant_media_assign_operation =
-            VariantMediaAssign::build(VariantMediaAssignVariables {
-                variant_id,
-                media_id,
-            });
-
-        if variant_media_assign_result.errors.is_some()
-            || variant_media_assign_result.data.as_ref().is_some_and(|x| {
-                x.variant_media_assign
-                    .as_ref()
-                    .is_some_and(|y| y.errors.len() > 0)
-            })
-        {
-            println!("{:?}", &variant_media_assign_operation.query);
-            println!("{:?}", &variant_media_assign_operation.variables);
-            println!("{:?}", &variant_media_assign_operation);
-        }
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "ProductUpdate")]
+pub struct ProductUpdate2 {
+    pub product: Option<Product>,
+    pub errors: Vec<ProductError>,
+}
 
-        if let Some(data) = &variant_media_assign_result.errors {
-            for dat in data {
-                if dat.message == "Signature has expired" {
-                    return Err(SaleorGraphqlError::SignatureExpired);
-                }
-                println!("dat");
-                return Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError));
-            }
-        }
-        return Ok(variant_media_assign_result);
+#[derive(cynic::InputObject, Debug)]
+pub struct ProductInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<AttributeValueInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub category: Option<&'a cynic::Id>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub charge_taxes: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub collections: Option<Vec<&'a cynic::Id>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Jsonstring>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub tax_class: Option<&'a cynic::Id>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub tax_code: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub seo: Option<SeoInput>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<WeightScalar>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f64>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Vec<MetadataInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub private_metadata: Option<Vec<MetadataInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub external_reference: Option<&'a str>,
+}
+
+/* --- CHANNEL, WAREHOUSE AND TAX CLASS LOOKUP --- */
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query")]
+pub struct ShopSetup {
+    pub channels: Option<Vec<Channel>>,
+    #[arguments(first: 100)]
+    pub warehouses: Option<WarehouseCountableConnection>,
+    #[arguments(first: 100)]
+    pub tax_classes: Option<TaxClassCountableConnection>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Channel {
+    pub id: cynic::Id,
+    pub slug: String,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct WarehouseCountableConnection {
+    pub edges: Vec<WarehouseCountableEdge>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct WarehouseCountableEdge {
+    pub node: Warehouse,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Warehouse {
+    pub id: cynic::Id,
+    pub slug: String,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct TaxClassCountableConnection {
+    pub edges: Vec<TaxClassCountableEdge>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct TaxClassCountableEdge {
+    pub node: TaxClass,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct TaxClass {
+    pub id: cynic::Id,
+    pub name: String,
+}
+
+/* --- CREATING MISSING CHANNEL, WAREHOUSE AND TAX CLASS --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct CreateChannelVariables<'a> {
+    pub input: ChannelCreateInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "CreateChannelVariables")]
+pub struct CreateChannel {
+    #[arguments(input: $input)]
+    pub channel_create: Option<ChannelCreate>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ChannelCreate {
+    pub errors: Vec<ChannelError>,
+    pub channel: Option<Channel>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ChannelError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: ChannelErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChannelErrorCode {
+    AlreadyExists,
+    GraphqlError,
+    Invalid,
+    NotFound,
+    Required,
+    Unique,
+    ChannelsCurrencyMustBeTheSame,
+    ChannelWithOrders,
+    DuplicatedInputItem,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct ChannelCreateInput<'a> {
+    pub name: &'a str,
+    pub slug: &'a str,
+    pub currency_code: &'a str,
+    pub default_country: CountryCode,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub add_warehouses: Option<Vec<&'a cynic::Id>>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct UpdateChannelVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: ChannelUpdateInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "UpdateChannelVariables")]
+pub struct UpdateChannel {
+    #[arguments(id: $id, input: $input)]
+    pub channel_update: Option<ChannelUpdate>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ChannelUpdate {
+    pub errors: Vec<ChannelError>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct ChannelUpdateInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub add_warehouses: Option<Vec<&'a cynic::Id>>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct CreateWarehouseVariables<'a> {
+    pub input: WarehouseCreateInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "CreateWarehouseVariables")]
+pub struct CreateWarehouse {
+    #[arguments(input: $input)]
+    pub create_warehouse: Option<WarehouseCreate>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct WarehouseCreate {
+    pub errors: Vec<WarehouseError>,
+    pub warehouse: Option<Warehouse>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct WarehouseError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: WarehouseErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WarehouseErrorCode {
+    AlreadyExists,
+    GraphqlError,
+    Invalid,
+    NotFound,
+    Required,
+    Unique,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct WarehouseCreateInput<'a> {
+    pub name: &'a str,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<&'a str>,
+    pub address: AddressInput<'a>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct AddressInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub company_name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none", rename = "streetAddress1")]
+    pub street_address_1: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none", rename = "streetAddress2")]
+    pub street_address_2: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub city: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub country: Option<CountryCode>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<&'a str>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct CreateTaxClassVariables<'a> {
+    pub input: TaxClassCreateInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "CreateTaxClassVariables")]
+pub struct CreateTaxClass {
+    #[arguments(input: $input)]
+    pub tax_class_create: Option<TaxClassCreate>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct TaxClassCreate {
+    pub errors: Vec<TaxClassCreateError>,
+    pub tax_class: Option<TaxClass>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct TaxClassCreateError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: TaxClassCreateErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaxClassCreateErrorCode {
+    GraphqlError,
+    Invalid,
+    NotFound,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct TaxClassCreateInput<'a> {
+    pub name: &'a str,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CountryCode {
+    Af,
+    Ax,
+    Al,
+    Dz,
+    As,
+    Ad,
+    Ao,
+    Ai,
+    Aq,
+    Ag,
+    Ar,
+    Am,
+    Aw,
+    Au,
+    At,
+    Az,
+    Bs,
+    Bh,
+    Bd,
+    Bb,
+    By,
+    Be,
+    Bz,
+    Bj,
+    Bm,
+    Bt,
+    Bo,
+    Bq,
+    Ba,
+    Bw,
+    Bv,
+    Br,
+    Io,
+    Bn,
+    Bg,
+    Bf,
+    Bi,
+    Cv,
+    Kh,
+    Cm,
+    Ca,
+    Ky,
+    Cf,
+    Td,
+    Cl,
+    Cn,
+    Cx,
+    Cc,
+    Co,
+    Km,
+    Cg,
+    Cd,
+    Ck,
+    Cr,
+    Ci,
+    Hr,
+    Cu,
+    Cw,
+    Cy,
+    Cz,
+    Dk,
+    Dj,
+    Dm,
+    Do,
+    Ec,
+    Eg,
+    Sv,
+    Gq,
+    Er,
+    Ee,
+    Sz,
+    Et,
+    Eu,
+    Fk,
+    Fo,
+    Fj,
+    Fi,
+    Fr,
+    Gf,
+    Pf,
+    Tf,
+    Ga,
+    Gm,
+    Ge,
+    De,
+    Gh,
+    Gi,
+    Gr,
+    Gl,
+    Gd,
+    Gp,
+    Gu,
+    Gt,
+    Gg,
+    Gn,
+    Gw,
+    Gy,
+    Ht,
+    Hm,
+    Va,
+    Hn,
+    Hk,
+    Hu,
+    Is,
+    In,
+    Id,
+    Ir,
+    Iq,
+    Ie,
+    Im,
+    Il,
+    It,
+    Jm,
+    Jp,
+    Je,
+    Jo,
+    Kz,
+    Ke,
+    Ki,
+    Kw,
+    Kg,
+    La,
+    Lv,
+    Lb,
+    Ls,
+    Lr,
+    Ly,
+    Li,
+    Lt,
+    Lu,
+    Mo,
+    Mg,
+    Mw,
+    My,
+    Mv,
+    Ml,
+    Mt,
+    Mh,
+    Mq,
+    Mr,
+    Mu,
+    Yt,
+    Mx,
+    Fm,
+    Md,
+    Mc,
+    Mn,
+    Me,
+    Ms,
+    Ma,
+    Mz,
+    Mm,
+    Na,
+    Nr,
+    Np,
+    Nl,
+    Nc,
+    Nz,
+    Ni,
+    Ne,
+    Ng,
+    Nu,
+    Nf,
+    Kp,
+    Mk,
+    Mp,
+    No,
+    Om,
+    Pk,
+    Pw,
+    Ps,
+    Pa,
+    Pg,
+    Py,
+    Pe,
+    Ph,
+    Pn,
+    Pl,
+    Pt,
+    Pr,
+    Qa,
+    Re,
+    Ro,
+    Ru,
+    Rw,
+    Bl,
+    Sh,
+    Kn,
+    Lc,
+    Mf,
+    Pm,
+    Vc,
+    Ws,
+    Sm,
+    St,
+    Sa,
+    Sn,
+    Rs,
+    Sc,
+    Sl,
+    Sg,
+    Sx,
+    Sk,
+    Si,
+    Sb,
+    So,
+    Za,
+    Gs,
+    Kr,
+    Ss,
+    Es,
+    Lk,
+    Sd,
+    Sr,
+    Sj,
+    Se,
+    Ch,
+    Sy,
+    Tw,
+    Tj,
+    Tz,
+    Th,
+    Tl,
+    Tg,
+    Tk,
+    To,
+    Tt,
+    Tn,
+    Tr,
+    Tm,
+    Tc,
+    Tv,
+    Ug,
+    Ua,
+    Ae,
+    Gb,
+    Um,
+    Us,
+    Uy,
+    Uz,
+    Vu,
+    Ve,
+    Vn,
+    Vg,
+    Vi,
+    Wf,
+    Eh,
+    Ye,
+    Zm,
+    Zw,
+}
+
+/* --- ATTRIBUTES --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct AttributeBySlugVariables<'a> {
+    pub slug: &'a str,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "AttributeBySlugVariables")]
+pub struct AttributeBySlug {
+    #[arguments(slug: $slug)]
+    pub attribute: Option<Attribute>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Attribute {
+    pub id: cynic::Id,
+    pub input_type: Option<AttributeInputTypeEnum>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct CreateAttributeVariables<'a> {
+    pub input: AttributeCreateInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "CreateAttributeVariables")]
+pub struct CreateAttribute {
+    #[arguments(input: $input)]
+    pub attribute_create: Option<AttributeCreate>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct AttributeCreate {
+    pub errors: Vec<AttributeError>,
+    pub attribute: Option<Attribute>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct AttributeError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: AttributeErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttributeErrorCode {
+    AlreadyExists,
+    GraphqlError,
+    Invalid,
+    NotFound,
+    Required,
+    Unique,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct AttributeCreateInput<'a> {
+    pub name: &'a str,
+    pub slug: Option<&'a str>,
+    #[cynic(rename = "type")]
+    pub type_: AttributeTypeEnum,
+    pub input_type: Option<AttributeInputTypeEnum>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub value_required: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub visible_in_storefront: Option<bool>,
+}
+
+#[derive(cynic::Enum, Clone, Copy, Debug)]
+pub enum AttributeTypeEnum {
+    ProductType,
+    PageType,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttributeInputTypeEnum {
+    Dropdown,
+    Multiselect,
+    File,
+    Reference,
+    Numeric,
+    RichText,
+    PlainText,
+    Swatch,
+    Boolean,
+    Date,
+    DateTime,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct AssignAttributesVariables<'a> {
+    pub product_type_id: &'a cynic::Id,
+    pub operations: Vec<ProductAttributeAssignInput<'a>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "AssignAttributesVariables")]
+pub struct AssignAttributes {
+    #[arguments(productTypeId: $product_type_id, operations: $operations)]
+    pub product_attribute_assign: Option<ProductAttributeAssign>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ProductAttributeAssign {
+    pub errors: Vec<ProductError>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct ProductAttributeAssignInput<'a> {
+    pub id: &'a cynic::Id,
+    #[cynic(rename = "type")]
+    pub type_: ProductAttributeType,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub variant_selection: Option<bool>,
+}
+
+#[derive(cynic::Enum, Clone, Copy, Debug)]
+pub enum ProductAttributeType {
+    Product,
+    Variant,
+}
+
+/* --- BULK PRODUCT CREATE --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct ProductBulkCreateVariables<'a> {
+    pub error_policy: ErrorPolicyEnum,
+    pub products: Vec<ProductBulkCreateInput<'a>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "ProductBulkCreateVariables")]
+pub struct ProductBulkCreate {
+    #[arguments(errorPolicy: $error_policy, products: $products)]
+    pub product_bulk_create: Option<ProductBulkCreate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "ProductBulkCreate")]
+pub struct ProductBulkCreate2 {
+    pub results: Vec<ProductBulkResult>,
+    pub errors: Vec<ProductBulkCreateError>,
+}
+
+/// One row of the bulk create, in the same order as the input
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ProductBulkResult {
+    pub product: Option<CreatedProduct>,
+    pub errors: Option<Vec<ProductBulkCreateError>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Product")]
+pub struct CreatedProduct {
+    pub id: cynic::Id,
+    pub variants: Option<Vec<ProductVariant>>,
+    pub media: Option<Vec<ProductMedia2>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ProductBulkCreateError {
+    pub path: Option<String>,
+    pub message: Option<String>,
+    pub code: ProductBulkCreateErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProductBulkCreateErrorCode {
+    AttributeAlreadyAssigned,
+    AttributeCannotBeAssigned,
+    AttributeVariantsDisabled,
+    Blank,
+    MaxLength,
+    DuplicatedInputItem,
+    GraphqlError,
+    Invalid,
+    InvalidPrice,
+    ProductWithoutCategory,
+    NotFound,
+    Required,
+    Unique,
+    ProductNotAssignedToChannel,
+    UnsupportedMediaProvider,
+}
+
+#[derive(cynic::Enum, Clone, Copy, Debug)]
+pub enum ErrorPolicyEnum {
+    IgnoreFailed,
+    RejectEverything,
+    RejectFailedRows,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct ProductBulkCreateInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<AttributeValueInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub category: Option<&'a cynic::Id>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Jsonstring>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub tax_class: Option<&'a cynic::Id>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub seo: Option<SeoInput>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<WeightScalar>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Vec<MetadataInput<'a>>>,
+    pub product_type: &'a cynic::Id,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub media: Option<Vec<MediaInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub channel_listings: Option<Vec<ProductChannelListingCreateInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub variants: Option<Vec<ProductVariantBulkCreateInput<'a>>>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct MediaInput<'a> {
+    pub alt: Option<&'a str>,
+    pub media_url: Option<String>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct ProductChannelListingCreateInput<'a> {
+    pub channel_id: &'a cynic::Id,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub is_published: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<DateTime>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub visible_in_listings: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub is_available_for_purchase: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub available_for_purchase_at: Option<DateTime>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct ProductVariantBulkCreateInput<'a> {
+    pub attributes: Vec<BulkAttributeValueInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub sku: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub track_inventory: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub preorder: Option<PreorderSettingsInput>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Vec<MetadataInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub stocks: Option<Vec<StockInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub channel_listings: Option<Vec<ProductVariantChannelListingAddInput<'a>>>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct BulkAttributeValueInput<'a> {
+    pub id: Option<&'a cynic::Id>,
+    pub values: Option<Vec<&'a str>>,
+}
+
+/* --- ATTACHMENTS --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct UploadFileVariables {
+    /// Stand-in, the multipart request puts the file here
+    pub file: Upload,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "UploadFileVariables")]
+pub struct UploadFile {
+    #[arguments(file: $file)]
+    pub file_upload: Option<UploadFile2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "FileUpload")]
+pub struct UploadFile2 {
+    pub uploaded_file: Option<UploadedFile>,
+    pub errors: Vec<UploadError>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "File")]
+pub struct UploadedFile {
+    pub url: String,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct UploadError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: UploadErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UploadErrorCode {
+    GraphqlError,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct UpdateMetadataVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: Vec<MetadataInput<'a>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "UpdateMetadataVariables")]
+pub struct UpdateMetadata {
+    #[arguments(id: $id, input: $input)]
+    pub update_metadata: Option<UpdateMetadata2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "UpdateMetadata")]
+pub struct UpdateMetadata2 {
+    pub errors: Vec<MetadataError>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct MetadataError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: MetadataErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MetadataErrorCode {
+    GraphqlError,
+    Invalid,
+    NotFound,
+    Required,
+    NotUpdated,
+}
+
+/* --- TRANSLATIONS --- */
+
+/// Saleor language codes, `SK`, `CS_CZ`... in the config
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LanguageCodeEnum {
+    Af,
+    AfNa,
+    AfZa,
+    Agq,
+    AgqCm,
+    Ak,
+    AkGh,
+    Am,
+    AmEt,
+    Ar,
+    ArAe,
+    ArBh,
+    ArDj,
+    ArDz,
+    ArEg,
+    ArEh,
+    ArEr,
+    ArIl,
+    ArIq,
+    ArJo,
+    ArKm,
+    ArKw,
+    ArLb,
+    ArLy,
+    ArMa,
+    ArMr,
+    ArOm,
+    ArPs,
+    ArQa,
+    ArSa,
+    ArSd,
+    ArSo,
+    ArSs,
+    ArSy,
+    ArTd,
+    ArTn,
+    ArYe,
+    As,
+    AsIn,
+    Asa,
+    AsaTz,
+    Ast,
+    AstEs,
+    Az,
+    AzCyrl,
+    AzCyrlAz,
+    AzLatn,
+    AzLatnAz,
+    Bas,
+    BasCm,
+    Be,
+    BeBy,
+    Bem,
+    BemZm,
+    Bez,
+    BezTz,
+    Bg,
+    BgBg,
+    Bm,
+    BmMl,
+    Bn,
+    BnBd,
+    BnIn,
+    Bo,
+    BoCn,
+    BoIn,
+    Br,
+    BrFr,
+    Brx,
+    BrxIn,
+    Bs,
+    BsCyrl,
+    BsCyrlBa,
+    BsLatn,
+    BsLatnBa,
+    Ca,
+    CaAd,
+    CaEs,
+    CaEsValencia,
+    CaFr,
+    CaIt,
+    Ccp,
+    CcpBd,
+    CcpIn,
+    Ce,
+    CeRu,
+    Ceb,
+    CebPh,
+    Cgg,
+    CggUg,
+    Chr,
+    ChrUs,
+    Ckb,
+    CkbIq,
+    CkbIr,
+    Cs,
+    CsCz,
+    Cu,
+    CuRu,
+    Cy,
+    CyGb,
+    Da,
+    DaDk,
+    DaGl,
+    Dav,
+    DavKe,
+    De,
+    DeAt,
+    DeBe,
+    DeCh,
+    DeDe,
+    DeIt,
+    DeLi,
+    DeLu,
+    Dje,
+    DjeNe,
+    Dsb,
+    DsbDe,
+    Dua,
+    DuaCm,
+    Dyo,
+    DyoSn,
+    Dz,
+    DzBt,
+    Ebu,
+    EbuKe,
+    Ee,
+    EeGh,
+    EeTg,
+    El,
+    ElCy,
+    ElGr,
+    En,
+    EnAe,
+    EnAg,
+    EnAi,
+    EnAs,
+    EnAt,
+    EnAu,
+    EnBb,
+    EnBe,
+    EnBi,
+    EnBm,
+    EnBs,
+    EnBw,
+    EnBz,
+    EnCa,
+    EnCc,
+    EnCh,
+    EnCk,
+    EnCm,
+    EnCx,
+    EnCy,
+    EnDe,
+    EnDg,
+    EnDk,
+    EnDm,
+    EnEr,
+    EnFi,
+    EnFj,
+    EnFk,
+    EnFm,
+    EnGb,
+    EnGd,
+    EnGg,
+    EnGh,
+    EnGi,
+    EnGm,
+    EnGu,
+    EnGy,
+    EnHk,
+    EnIe,
+    EnIl,
+    EnIm,
+    EnIn,
+    EnIo,
+    EnJe,
+    EnJm,
+    EnKe,
+    EnKi,
+    EnKn,
+    EnKy,
+    EnLc,
+    EnLr,
+    EnLs,
+    EnMg,
+    EnMh,
+    EnMo,
+    EnMp,
+    EnMs,
+    EnMt,
+    EnMu,
+    EnMw,
+    EnMy,
+    EnNa,
+    EnNf,
+    EnNg,
+    EnNl,
+    EnNr,
+    EnNu,
+    EnNz,
+    EnPg,
+    EnPh,
+    EnPk,
+    EnPn,
+    EnPr,
+    EnPw,
+    EnRw,
+    EnSb,
+    EnSc,
+    EnSd,
+    EnSe,
+    EnSg,
+    EnSh,
+    EnSi,
+    EnSl,
+    EnSs,
+    EnSx,
+    EnSz,
+    EnTc,
+    EnTk,
+    EnTo,
+    EnTt,
+    EnTv,
+    EnTz,
+    EnUg,
+    EnUm,
+    EnUs,
+    EnVc,
+    EnVg,
+    EnVi,
+    EnVu,
+    EnWs,
+    EnZa,
+    EnZm,
+    EnZw,
+    Eo,
+    Es,
+    EsAr,
+    EsBo,
+    EsBr,
+    EsBz,
+    EsCl,
+    EsCo,
+    EsCr,
+    EsCu,
+    EsDo,
+    EsEa,
+    EsEc,
+    EsEs,
+    EsGq,
+    EsGt,
+    EsHn,
+    EsIc,
+    EsMx,
+    EsNi,
+    EsPa,
+    EsPe,
+    EsPh,
+    EsPr,
+    EsPy,
+    EsSv,
+    EsUs,
+    EsUy,
+    EsVe,
+    Et,
+    EtEe,
+    Eu,
+    EuEs,
+    Ewo,
+    EwoCm,
+    Fa,
+    FaAf,
+    FaIr,
+    Ff,
+    FfAdlm,
+    FfAdlmBf,
+    FfAdlmCm,
+    FfAdlmGh,
+    FfAdlmGm,
+    FfAdlmGn,
+    FfAdlmGw,
+    FfAdlmLr,
+    FfAdlmMr,
+    FfAdlmNe,
+    FfAdlmNg,
+    FfAdlmSl,
+    FfAdlmSn,
+    FfLatn,
+    FfLatnBf,
+    FfLatnCm,
+    FfLatnGh,
+    FfLatnGm,
+    FfLatnGn,
+    FfLatnGw,
+    FfLatnLr,
+    FfLatnMr,
+    FfLatnNe,
+    FfLatnNg,
+    FfLatnSl,
+    FfLatnSn,
+    Fi,
+    FiFi,
+    Fil,
+    FilPh,
+    Fo,
+    FoDk,
+    FoFo,
+    Fr,
+    FrBe,
+    FrBf,
+    FrBi,
+    FrBj,
+    FrBl,
+    FrCa,
+    FrCd,
+    FrCf,
+    FrCg,
+    FrCh,
+    FrCi,
+    FrCm,
+    FrDj,
+    FrDz,
+    FrFr,
+    FrGa,
+    FrGf,
+    FrGn,
+    FrGp,
+    FrGq,
+    FrHt,
+    FrKm,
+    FrLu,
+    FrMa,
+    FrMc,
+    FrMf,
+    FrMg,
+    FrMl,
+    FrMq,
+    FrMr,
+    FrMu,
+    FrNc,
+    FrNe,
+    FrPf,
+    FrPm,
+    FrRe,
+    FrRw,
+    FrSc,
+    FrSn,
+    FrSy,
+    FrTd,
+    FrTg,
+    FrTn,
+    FrVu,
+    FrWf,
+    FrYt,
+    Fur,
+    FurIt,
+    Fy,
+    FyNl,
+    Ga,
+    GaGb,
+    GaIe,
+    Gd,
+    GdGb,
+    Gl,
+    GlEs,
+    Gsw,
+    GswCh,
+    GswFr,
+    GswLi,
+    Gu,
+    GuIn,
+    Guz,
+    GuzKe,
+    Gv,
+    GvIm,
+    Ha,
+    HaGh,
+    HaNe,
+    HaNg,
+    Haw,
+    HawUs,
+    He,
+    HeIl,
+    Hi,
+    HiIn,
+    Hr,
+    HrBa,
+    HrHr,
+    Hsb,
+    HsbDe,
+    Hu,
+    HuHu,
+    Hy,
+    HyAm,
+    Ia,
+    Id,
+    IdId,
+    Ig,
+    IgNg,
+    Ii,
+    IiCn,
+    Is,
+    IsIs,
+    It,
+    ItCh,
+    ItIt,
+    ItSm,
+    ItVa,
+    Ja,
+    JaJp,
+    Jgo,
+    JgoCm,
+    Jmc,
+    JmcTz,
+    Jv,
+    JvId,
+    Ka,
+    KaGe,
+    Kab,
+    KabDz,
+    Kam,
+    KamKe,
+    Kde,
+    KdeTz,
+    Kea,
+    KeaCv,
+    Khq,
+    KhqMl,
+    Ki,
+    KiKe,
+    Kk,
+    KkKz,
+    Kkj,
+    KkjCm,
+    Kl,
+    KlGl,
+    Kln,
+    KlnKe,
+    Km,
+    KmKh,
+    Kn,
+    KnIn,
+    Ko,
+    KoKp,
+    KoKr,
+    Kok,
+    KokIn,
+    Ks,
+    KsArab,
+    KsArabIn,
+    Ksb,
+    KsbTz,
+    Ksf,
+    KsfCm,
+    Ksh,
+    KshDe,
+    Ku,
+    KuTr,
+    Kw,
+    KwGb,
+    Ky,
+    KyKg,
+    Lag,
+    LagTz,
+    Lb,
+    LbLu,
+    Lg,
+    LgUg,
+    Lkt,
+    LktUs,
+    Ln,
+    LnAo,
+    LnCd,
+    LnCf,
+    LnCg,
+    Lo,
+    LoLa,
+    Lrc,
+    LrcIq,
+    LrcIr,
+    Lt,
+    LtLt,
+    Lu,
+    LuCd,
+    Luo,
+    LuoKe,
+    Luy,
+    LuyKe,
+    Lv,
+    LvLv,
+    Mai,
+    MaiIn,
+    Mas,
+    MasKe,
+    MasTz,
+    Mer,
+    MerKe,
+    Mfe,
+    MfeMu,
+    Mg,
+    MgMg,
+    Mgh,
+    MghMz,
+    Mgo,
+    MgoCm,
+    Mi,
+    MiNz,
+    Mk,
+    MkMk,
+    Ml,
+    MlIn,
+    Mn,
+    MnMn,
+    Mni,
+    MniBeng,
+    MniBengIn,
+    Mr,
+    MrIn,
+    Ms,
+    MsBn,
+    MsId,
+    MsMy,
+    MsSg,
+    Mt,
+    MtMt,
+    Mua,
+    MuaCm,
+    My,
+    MyMm,
+    Mzn,
+    MznIr,
+    Naq,
+    NaqNa,
+    Nb,
+    NbNo,
+    NbSj,
+    Nd,
+    NdZw,
+    Nds,
+    NdsDe,
+    NdsNl,
+    Ne,
+    NeIn,
+    NeNp,
+    Nl,
+    NlAw,
+    NlBe,
+    NlBq,
+    NlCw,
+    NlNl,
+    NlSr,
+    NlSx,
+    Nmg,
+    NmgCm,
+    Nn,
+    NnNo,
+    Nnh,
+    NnhCm,
+    Nus,
+    NusSs,
+    Nyn,
+    NynUg,
+    Om,
+    OmEt,
+    OmKe,
+    Or,
+    OrIn,
+    Os,
+    OsGe,
+    OsRu,
+    Pa,
+    PaArab,
+    PaArabPk,
+    PaGuru,
+    PaGuruIn,
+    Pcm,
+    PcmNg,
+    Pl,
+    PlPl,
+    Prg,
+    Ps,
+    PsAf,
+    PsPk,
+    Pt,
+    PtAo,
+    PtBr,
+    PtCh,
+    PtCv,
+    PtGq,
+    PtGw,
+    PtLu,
+    PtMo,
+    PtMz,
+    PtPt,
+    PtSt,
+    PtTl,
+    Qu,
+    QuBo,
+    QuEc,
+    QuPe,
+    Rm,
+    RmCh,
+    Rn,
+    RnBi,
+    Ro,
+    RoMd,
+    RoRo,
+    Rof,
+    RofTz,
+    Ru,
+    RuBy,
+    RuKg,
+    RuKz,
+    RuMd,
+    RuRu,
+    RuUa,
+    Rw,
+    RwRw,
+    Rwk,
+    RwkTz,
+    Sah,
+    SahRu,
+    Saq,
+    SaqKe,
+    Sat,
+    SatOlck,
+    SatOlckIn,
+    Sbp,
+    SbpTz,
+    Sd,
+    SdArab,
+    SdArabPk,
+    SdDeva,
+    SdDevaIn,
+    Se,
+    SeFi,
+    SeNo,
+    SeSe,
+    Seh,
+    SehMz,
+    Ses,
+    SesMl,
+    Sg,
+    SgCf,
+    Shi,
+    ShiLatn,
+    ShiLatnMa,
+    ShiTfng,
+    ShiTfngMa,
+    Si,
+    SiLk,
+    Sk,
+    SkSk,
+    Sl,
+    SlSi,
+    Smn,
+    SmnFi,
+    Sn,
+    SnZw,
+    So,
+    SoDj,
+    SoEt,
+    SoKe,
+    SoSo,
+    Sq,
+    SqAl,
+    SqMk,
+    SqXk,
+    Sr,
+    SrCyrl,
+    SrCyrlBa,
+    SrCyrlMe,
+    SrCyrlRs,
+    SrCyrlXk,
+    SrLatn,
+    SrLatnBa,
+    SrLatnMe,
+    SrLatnRs,
+    SrLatnXk,
+    Su,
+    SuLatn,
+    SuLatnId,
+    Sv,
+    SvAx,
+    SvFi,
+    SvSe,
+    Sw,
+    SwCd,
+    SwKe,
+    SwTz,
+    SwUg,
+    Ta,
+    TaIn,
+    TaLk,
+    TaMy,
+    TaSg,
+    Te,
+    TeIn,
+    Teo,
+    TeoKe,
+    TeoUg,
+    Tg,
+    TgTj,
+    Th,
+    ThTh,
+    Ti,
+    TiEr,
+    TiEt,
+    Tk,
+    TkTm,
+    To,
+    ToTo,
+    Tr,
+    TrCy,
+    TrTr,
+    Tt,
+    TtRu,
+    Twq,
+    TwqNe,
+    Tzm,
+    TzmMa,
+    Ug,
+    UgCn,
+    Uk,
+    UkUa,
+    Ur,
+    UrIn,
+    UrPk,
+    Uz,
+    UzArab,
+    UzArabAf,
+    UzCyrl,
+    UzCyrlUz,
+    UzLatn,
+    UzLatnUz,
+    Vai,
+    VaiLatn,
+    VaiLatnLr,
+    VaiVaii,
+    VaiVaiiLr,
+    Vi,
+    ViVn,
+    Vo,
+    Vun,
+    VunTz,
+    Wae,
+    WaeCh,
+    Wo,
+    WoSn,
+    Xh,
+    XhZa,
+    Xog,
+    XogUg,
+    Yav,
+    YavCm,
+    Yi,
+    Yo,
+    YoBj,
+    YoNg,
+    Yue,
+    YueHans,
+    YueHansCn,
+    YueHant,
+    YueHantHk,
+    Zgh,
+    ZghMa,
+    Zh,
+    ZhHans,
+    ZhHansCn,
+    ZhHansHk,
+    ZhHansMo,
+    ZhHansSg,
+    ZhHant,
+    ZhHantHk,
+    ZhHantMo,
+    ZhHantTw,
+    Zu,
+    ZuZa,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct TranslateVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: TranslationInput<'a>,
+    pub language_code: LanguageCodeEnum,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "TranslateVariables")]
+pub struct ProductTranslate {
+    #[arguments(id: $id, input: $input, languageCode: $language_code)]
+    pub product_translate: Option<ProductTranslate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "ProductTranslate")]
+pub struct ProductTranslate2 {
+    pub errors: Vec<TranslationError>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "TranslateVariables")]
+pub struct CategoryTranslate {
+    #[arguments(id: $id, input: $input, languageCode: $language_code)]
+    pub category_translate: Option<CategoryTranslate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "CategoryTranslate")]
+pub struct CategoryTranslate2 {
+    pub errors: Vec<TranslationError>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct TranslationInput<'a> {
+    pub seo_title: Option<String>,
+    pub seo_description: Option<String>,
+    pub name: Option<&'a str>,
+    pub description: Option<Jsonstring>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct TranslationError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: TranslationErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TranslationErrorCode {
+    GraphqlError,
+    Invalid,
+    NotFound,
+    Required,
+}
+
+/* --- CUSTOMERS --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct CustomerCreateVariables<'a> {
+    pub input: UserCreateInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "CustomerCreateVariables")]
+pub struct CustomerCreate {
+    #[arguments(input: $input)]
+    pub customer_create: Option<CustomerCreate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "CustomerCreate")]
+pub struct CustomerCreate2 {
+    pub user: Option<CreatedCustomer>,
+    pub errors: Vec<CustomerError>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "User")]
+pub struct CreatedCustomer {
+    pub id: cynic::Id,
+}
+
+/// `AccountError` with its code, the token mutations don't need that one
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "AccountError")]
+pub struct CustomerError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: AccountErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccountErrorCode {
+    ActivateOwnAccount,
+    ActivateSuperuserAccount,
+    DuplicatedInputItem,
+    DeactivateOwnAccount,
+    DeactivateSuperuserAccount,
+    DeleteNonStaffUser,
+    DeleteOwnAccount,
+    DeleteStaffAccount,
+    DeleteSuperuserAccount,
+    GraphqlError,
+    Inactive,
+    Invalid,
+    InvalidPassword,
+    LeftNotManageablePermission,
+    InvalidCredentials,
+    NotFound,
+    OutOfScopeUser,
+    OutOfScopeGroup,
+    OutOfScopePermission,
+    PasswordEntirelyNumeric,
+    PasswordTooCommon,
+    PasswordTooShort,
+    PasswordTooSimilar,
+    PasswordResetAlreadyRequested,
+    Required,
+    Unique,
+    JwtSignatureExpired,
+    JwtInvalidToken,
+    JwtDecodeError,
+    JwtMissingToken,
+    JwtInvalidCsrfToken,
+    ChannelInactive,
+    MissingChannelSlug,
+    AccountNotConfirmed,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct CustomerBulkUpdateVariables<'a> {
+    pub customers: Vec<CustomerBulkUpdateInput<'a>>,
+    pub error_policy: ErrorPolicyEnum,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "CustomerBulkUpdateVariables")]
+pub struct CustomerBulkUpdate {
+    #[arguments(customers: $customers, errorPolicy: $error_policy)]
+    pub customer_bulk_update: Option<CustomerBulkUpdate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "CustomerBulkUpdate")]
+pub struct CustomerBulkUpdate2 {
+    pub results: Vec<CustomerBulkResult>,
+    pub errors: Vec<CustomerBulkUpdateError>,
+}
+
+/// One row of the bulk update, in the same order as the input
+#[derive(cynic::QueryFragment, Debug)]
+pub struct CustomerBulkResult {
+    pub customer: Option<CreatedCustomer>,
+    pub errors: Option<Vec<CustomerBulkUpdateError>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct CustomerBulkUpdateError {
+    pub path: Option<String>,
+    pub message: Option<String>,
+    pub code: CustomerBulkUpdateErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CustomerBulkUpdateErrorCode {
+    Blank,
+    DuplicatedInputItem,
+    GraphqlError,
+    Invalid,
+    Required,
+    Unique,
+    NotFound,
+    MaxLength,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct CustomerBulkUpdateInput<'a> {
+    pub id: Option<&'a cynic::Id>,
+    pub input: CustomerInput<'a>,
+}
+
+/// What the old account carries over. There's deliberately no password, customers set a new one
+/// through the password reset
+#[derive(cynic::InputObject, Debug)]
+pub struct CustomerInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub default_billing_address: Option<AddressInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub default_shipping_address: Option<AddressInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub is_active: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Vec<MetadataInput<'a>>>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct UserCreateInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub default_billing_address: Option<AddressInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub default_shipping_address: Option<AddressInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<&'a str>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub is_active: Option<bool>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Vec<MetadataInput<'a>>>,
+}
+
+impl<'a> From<CustomerInput<'a>> for UserCreateInput<'a> {
+    fn from(input: CustomerInput<'a>) -> Self {
+        UserCreateInput {
+            default_billing_address: input.default_billing_address,
+            default_shipping_address: input.default_shipping_address,
+            first_name: input.first_name,
+            last_name: input.last_name,
+            email: input.email,
+            is_active: input.is_active,
+            metadata: input.metadata,
+        }
+    }
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "ExistingObjectsVariables")]
+pub struct ExistingCustomers {
+    #[arguments(first: 100, after: $after, filter: { metadata: [{ key: "old_id" }] })]
+    pub customers: Option<UserCountableConnection>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct UserCountableConnection {
+    pub page_info: PageInfo,
+    pub edges: Vec<UserCountableEdge>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct UserCountableEdge {
+    pub node: ExistingCustomer,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "User")]
+pub struct ExistingCustomer {
+    pub id: cynic::Id,
+    #[arguments(key: "old_id")]
+    pub metafield: Option<String>,
+}
+
+/* --- ORDERS --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct OrderBulkCreateVariables<'a> {
+    pub error_policy: ErrorPolicyEnum,
+    pub orders: Vec<OrderBulkCreateInput<'a>>,
+    pub stock_update_policy: StockUpdatePolicyEnum,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "OrderBulkCreateVariables")]
+pub struct OrderBulkCreate {
+    #[arguments(errorPolicy: $error_policy, orders: $orders, stockUpdatePolicy: $stock_update_policy)]
+    pub order_bulk_create: Option<OrderBulkCreate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "OrderBulkCreate")]
+pub struct OrderBulkCreate2 {
+    pub results: Vec<OrderBulkCreateResult>,
+    pub errors: Vec<OrderBulkCreateError>,
+}
+
+/// One row of the bulk create, in the same order as the input
+#[derive(cynic::QueryFragment, Debug)]
+pub struct OrderBulkCreateResult {
+    pub order: Option<CreatedOrder>,
+    pub errors: Option<Vec<OrderBulkCreateError>>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Order")]
+pub struct CreatedOrder {
+    pub id: cynic::Id,
+    pub number: String,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct OrderBulkCreateError {
+    pub path: Option<String>,
+    pub message: Option<String>,
+    pub code: Option<OrderBulkCreateErrorCode>,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OrderBulkCreateErrorCode {
+    GraphqlError,
+    Required,
+    Invalid,
+    NotFound,
+    Unique,
+    BulkLimit,
+    TooManyIdentifiers,
+    FutureDate,
+    InvalidQuantity,
+    PriceError,
+    NoteLength,
+    InsufficientStock,
+    NonExistingStock,
+    NoRelatedOrderLine,
+    NegativeIndex,
+    OrderLineFulfillmentLineMismatch,
+    MetadataKeyRequired,
+    IncorrectCurrency,
+}
+
+/// `DRAFT`, `UNFULFILLED`, `FULFILLED`, `CANCELED`... in the config
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OrderStatus {
+    Draft,
+    Unconfirmed,
+    Unfulfilled,
+    PartiallyFulfilled,
+    PartiallyReturned,
+    Returned,
+    Fulfilled,
+    Canceled,
+    Expired,
+}
+
+#[derive(cynic::Enum, Clone, Copy, Debug)]
+pub enum StockUpdatePolicyEnum {
+    Skip,
+    Update,
+    Force,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct OrderBulkCreateInput<'a> {
+    pub external_reference: Option<&'a str>,
+    pub channel: &'a str,
+    pub created_at: DateTime,
+    pub status: Option<OrderStatus>,
+    pub user: OrderBulkCreateUserInput<'a>,
+    pub billing_address: AddressInput<'a>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub shipping_address: Option<AddressInput<'a>>,
+    pub currency: &'a str,
+    pub metadata: Option<Vec<MetadataInput<'a>>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub customer_note: Option<&'a str>,
+    pub language_code: LanguageCodeEnum,
+    pub display_gross_prices: Option<bool>,
+    pub lines: Vec<OrderBulkCreateOrderLineInput<'a>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub delivery_method: Option<OrderBulkCreateDeliveryMethodInput<'a>>,
+}
+
+/// Either the saleor id or the email, saleor refuses both at once
+#[derive(cynic::InputObject, Debug)]
+pub struct OrderBulkCreateUserInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub id: Option<&'a cynic::Id>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub email: Option<&'a str>,
+}
+
+/// Either the variant id or the SKU, saleor refuses both at once
+#[derive(cynic::InputObject, Debug)]
+pub struct OrderBulkCreateOrderLineInput<'a> {
+    pub variant_id: &'a cynic::Id,
+    pub product_name: Option<&'a str>,
+    pub created_at: DateTime,
+    pub is_shipping_required: bool,
+    pub is_gift_card: bool,
+    pub quantity: i32,
+    pub total_price: TaxedMoneyInput,
+    pub undiscounted_total_price: TaxedMoneyInput,
+    pub warehouse: &'a cynic::Id,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub tax_rate: Option<PositiveDecimal>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct OrderBulkCreateDeliveryMethodInput<'a> {
+    pub shipping_method_name: Option<&'a str>,
+    pub shipping_price: Option<TaxedMoneyInput>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub shipping_tax_rate: Option<PositiveDecimal>,
+}
+
+#[derive(cynic::InputObject, Debug, Clone)]
+pub struct TaxedMoneyInput {
+    pub gross: PositiveDecimal,
+    pub net: PositiveDecimal,
+}
+
+/* --- SALES --- */
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct SaleCreateVariables<'a> {
+    pub input: SaleInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "SaleCreateVariables")]
+pub struct SaleCreate {
+    #[arguments(input: $input)]
+    pub sale_create: Option<SaleCreate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "SaleCreate")]
+pub struct SaleCreate2 {
+    pub sale: Option<CreatedSale>,
+    pub errors: Vec<DiscountError>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Sale")]
+pub struct CreatedSale {
+    pub id: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "ExistingObjectsVariables")]
+pub struct ExistingSales {
+    #[arguments(first: 100, after: $after)]
+    pub sales: Option<SaleCountableConnection>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct SaleCountableConnection {
+    pub page_info: PageInfo,
+    pub edges: Vec<SaleCountableEdge>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct SaleCountableEdge {
+    pub node: ExistingSale,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Sale")]
+pub struct ExistingSale {
+    pub id: cynic::Id,
+    pub name: String,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct SaleCataloguesAddVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: CatalogueInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "SaleCataloguesAddVariables")]
+pub struct SaleCataloguesAdd {
+    #[arguments(id: $id, input: $input)]
+    pub sale_catalogues_add: Option<SaleAddCatalogues>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct SaleAddCatalogues {
+    pub sale: Option<CreatedSale>,
+    pub errors: Vec<DiscountError>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct SaleChannelListingUpdateVariables<'a> {
+    pub id: &'a cynic::Id,
+    pub input: SaleChannelListingInput<'a>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(
+    graphql_type = "Mutation",
+    variables = "SaleChannelListingUpdateVariables"
+)]
+pub struct SaleChannelListingUpdate {
+    #[arguments(id: $id, input: $input)]
+    pub sale_channel_listing_update: Option<SaleChannelListingUpdate2>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "SaleChannelListingUpdate")]
+pub struct SaleChannelListingUpdate2 {
+    pub sale: Option<CreatedSale>,
+    pub errors: Vec<DiscountError>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct DiscountError {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: DiscountErrorCode,
+}
+
+#[derive(cynic::Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiscountErrorCode {
+    AlreadyExists,
+    GraphqlError,
+    Invalid,
+    NotFound,
+    Required,
+    Unique,
+    CannotManageProductWithoutVariant,
+    DuplicatedInputItem,
+}
+
+#[derive(cynic::Enum, Clone, Copy, Debug)]
+pub enum DiscountValueTypeEnum {
+    Fixed,
+    Percentage,
+}
+
+/// The value isn't here, sales have theirs per channel
+#[derive(cynic::InputObject, Debug)]
+pub struct SaleInput<'a> {
+    pub name: Option<&'a str>,
+    #[cynic(rename = "type")]
+    pub type_: Option<DiscountValueTypeEnum>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct CatalogueInput<'a> {
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub products: Option<Vec<&'a cynic::Id>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub variants: Option<Vec<&'a cynic::Id>>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<&'a cynic::Id>>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct SaleChannelListingInput<'a> {
+    pub add_channels: Option<Vec<SaleChannelListingAddInput<'a>>>,
+}
+
+#[derive(cynic::InputObject, Debug)]
+pub struct SaleChannelListingAddInput<'a> {
+    pub channel_id: &'a cynic::Id,
+    pub discount_value: PositiveDecimal,
+}
+
+/* --- MUTATION ERRORS --- */
+
+/// Points `Saleor::mutate` at the `errors` of each mutation's payload
+macro_rules! mutation_errors {
+    ($code:ty: $($mutation:ty => $payload:ident),* $(,)?) => {$(
+        impl MutationErrors for $mutation {
+            type Code = $code;
+            fn mutation_errors(&self) -> Vec<MutationError<$code>> {
+                self.$payload
+                    .iter()
+                    .flat_map(|payload| &payload.errors)
+                    .map(|e| MutationError {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                        code: e.code,
+                    })
+                    .collect()
+            }
+        }
+    )*};
+}
+
+mutation_errors!(ProductErrorCode:
+    CreateCategory => category_create,
+    UpdateCategory => category_update,
+    CreateProductType => product_type_create,
+    AssignAttributes => product_attribute_assign,
+    ProductCreate => product_create,
+    ProductUpdate => product_update,
+    ProductChannelListingUpdate => product_channel_listing_update,
+    VariantCreate => product_variant_create,
+    VariantChannelListingUpdate => product_variant_channel_listing_update,
+    ProductMediaCreate => product_media_create,
+    VariantMediaAssign => variant_media_assign,
+);
+mutation_errors!(ChannelErrorCode: CreateChannel => channel_create, UpdateChannel => channel_update);
+mutation_errors!(WarehouseErrorCode: CreateWarehouse => create_warehouse);
+mutation_errors!(TaxClassCreateErrorCode: CreateTaxClass => tax_class_create);
+mutation_errors!(AttributeErrorCode: CreateAttribute => attribute_create);
+mutation_errors!(UploadErrorCode: UploadFile => file_upload);
+mutation_errors!(MetadataErrorCode: UpdateMetadata => update_metadata);
+mutation_errors!(AccountErrorCode: CustomerCreate => customer_create);
+mutation_errors!(DiscountErrorCode:
+    SaleCreate => sale_create,
+    SaleCataloguesAdd => sale_catalogues_add,
+    SaleChannelListingUpdate => sale_channel_listing_update,
+);
+mutation_errors!(TranslationErrorCode:
+    ProductTranslate => product_translate,
+    CategoryTranslate => category_translate,
+);
+
+impl MutationErrors for ProductBulkCreate {
+    type Code = ProductBulkCreateErrorCode;
+    /// Only when not a single row got through, otherwise every row carries its own errors and the
+    /// created ones mustn't get lost
+    fn mutation_errors(&self) -> Vec<MutationError<ProductBulkCreateErrorCode>> {
+        self.product_bulk_create
+            .iter()
+            .filter(|bulk| bulk.results.iter().all(|r| r.product.is_none()))
+            .flat_map(|bulk| &bulk.errors)
+            .map(ProductBulkCreateError::to_mutation_error)
+            .collect()
+    }
+}
+
+impl ProductBulkCreateError {
+    pub fn to_mutation_error(&self) -> MutationError<ProductBulkCreateErrorCode> {
+        MutationError {
+            field: self.path.clone(),
+            message: self.message.clone(),
+            code: self.code,
+        }
+    }
+}
+
+impl MutationErrors for CustomerBulkUpdate {
+    type Code = CustomerBulkUpdateErrorCode;
+    /// Only when not a single row got through, like `ProductBulkCreate`
+    fn mutation_errors(&self) -> Vec<MutationError<CustomerBulkUpdateErrorCode>> {
+        self.customer_bulk_update
+            .iter()
+            .filter(|bulk| bulk.results.iter().all(|r| r.customer.is_none()))
+            .flat_map(|bulk| &bulk.errors)
+            .map(CustomerBulkUpdateError::to_mutation_error)
+            .collect()
+    }
+}
+
+impl CustomerBulkUpdateError {
+    pub fn to_mutation_error(&self) -> MutationError<CustomerBulkUpdateErrorCode> {
+        MutationError {
+            field: self.path.clone(),
+            message: self.message.clone(),
+            code: self.code,
+        }
+    }
+}
+
+impl MutationErrors for OrderBulkCreate {
+    type Code = OrderBulkCreateErrorCode;
+    /// Only when not a single row got through, like `ProductBulkCreate`
+    fn mutation_errors(&self) -> Vec<MutationError<OrderBulkCreateErrorCode>> {
+        self.order_bulk_create
+            .iter()
+            .filter(|bulk| bulk.results.iter().all(|r| r.order.is_none()))
+            .flat_map(|bulk| &bulk.errors)
+            .map(OrderBulkCreateError::to_mutation_error)
+            .collect()
+    }
+}
+
+impl OrderBulkCreateError {
+    /// Saleor leaves the code out for some errors, those count as `GRAPHQL_ERROR`
+    pub fn to_mutation_error(&self) -> MutationError<OrderBulkCreateErrorCode> {
+        MutationError {
+            field: self.path.clone(),
+            message: self.message.clone(),
+            code: self.code.unwrap_or(OrderBulkCreateErrorCode::GraphqlError),
+        }
+    }
+}
+
+/* --- ACTUAL QUERIES--- */
+
+pub fn product_create_operation<'a>(
+    category_id: Option<&'a Id>,
+    product: &'a FinalProduct,
+    old_id: &'a str,
+    tax_class_id: &'a Id,
+    product_type_id: &'a Id,
+    type_attributes: &[String],
+    attributes: &'a AttributeIds,
+) -> cynic::Operation<ProductCreate, ProductCreateVariables<'a>> {
+    ProductCreate::build(ProductCreateVariables {
+        input: product_create_input(
+            category_id,
+            product,
+            old_id,
+            tax_class_id,
+            product_type_id,
+            type_attributes,
+            attributes,
+        ),
+    })
+}
+
+pub fn product_create_input<'a>(
+    category_id: Option<&'a Id>,
+    product: &'a FinalProduct,
+    old_id: &'a str,
+    tax_class_id: &'a Id,
+    product_type_id: &'a Id,
+    type_attributes: &[String],
+    attributes: &'a AttributeIds,
+) -> ProductCreateInput<'a> {
+    let description = Jsonstring::from_string(product.product.description.clone());
+    let weight = product.product.weight.map(|w| WeightScalar(w.to_string()));
+    let attribute_values = product_attribute_values(product, type_attributes, attributes);
+    let mut metadata = vec![
+        MetadataInput {
+            key: "short_description",
+            value: product.product.short_description.as_str(),
+        },
+        MetadataInput {
+            key: "old_id",
+            value: old_id,
+        },
+    ];
+    metadata.extend(availability_metadata(product));
+    metadata.extend(date_metadata(product));
+    ProductCreateInput {
+        attributes: (!attribute_values.is_empty()).then_some(attribute_values),
+        category: category_id,
+        charge_taxes: Some(true),
+        collections: None,
+        description: Some(description),
+        name: Some(product.product.name.as_str()),
+        slug: Some(product.slug.as_str()),
+        tax_class: Some(tax_class_id),
+        tax_code: None,
+        seo: Some(product_seo(&product.product)),
+        weight,
+        rating: None,
+        metadata: Some(metadata),
+        private_metadata: None,
+        external_reference: None,
+        product_type: product_type_id,
+    }
+}
+
+/// Values for the attributes the product type has, saleor refuses the rest
+pub fn product_attribute_values<'a>(
+    product: &'a FinalProduct,
+    type_attributes: &[String],
+    attributes: &'a AttributeIds,
+) -> Vec<AttributeValueInput<'a>> {
+    product
+        .attributes
+        .iter()
+        .filter(|(slug, _)| type_attributes.contains(slug))
+        .filter_map(|(slug, value)| {
+            let (id, input_type) = attributes.product.get(slug)?;
+            let mut input = AttributeValueInput {
+                id: Some(id),
+                ..Default::default()
+            };
+            match input_type {
+                AttributeInputTypeEnum::Numeric => input.numeric = Some(value.as_str()),
+                AttributeInputTypeEnum::PlainText => input.plain_text = Some(value.as_str()),
+                AttributeInputTypeEnum::Boolean => input.boolean = Some(value != "0"),
+                _ => input.values = Some(vec![value.as_str()]),
+            }
+            Some(input)
+        })
+        .collect()
+}
+
+/// Saleor id and attribute slugs of the product's type
+pub fn product_type_of(product: &FinalProduct, default_product_type_id: &Id) -> (Id, Vec<String>) {
+    //If product type has saleor_id, use that, else use default product type id. Product types
+    //get created before any product, so one without an id failed there
+    let mut product_type_id: cynic::Id = default_product_type_id.clone();
+    let mut type_attributes: Vec<String> = vec![];
+    if let Some(category) = &product.category {
+        if let Some(product_type) = &category.read().product_type {
+            let product_type = product_type.read();
+            if let Some(prod_type_saleor_id) = &product_type.saleor_id {
+                product_type_id = prod_type_saleor_id.clone();
+                type_attributes = product_type.attributes.clone();
+            }
+        }
+    }
+    (product_type_id, type_attributes)
+}
+
+pub async fn create_product(
+    category_id: Option<&Id>,
+    product: &FinalProduct,
+    tax_class_id: &Id,
+    default_product_type_id: &Id,
+    attributes: &AttributeIds,
+    old_id: u32,
+    saleor: &Saleor,
+) -> Result<cynic::Id, SaleorError<ProductErrorCode>> {
+    let (product_type_id, type_attributes) = product_type_of(product, default_product_type_id);
+    let old_id = old_id.to_string();
+    let create_product_operation = product_create_operation(
+        category_id,
+        product,
+        &old_id,
+        tax_class_id,
+        &product_type_id,
+        &type_attributes,
+        attributes,
+    );
+
+    Ok(saleor
+        .mutate(create_product_operation)
+        .await?
+        .product_create
+        .and_then(|c| c.product)
+        .ok_or(SaleorError::NoData)?
+        .id)
+}
+
+/// The main channel plus every channel the product has a price in
+pub fn listed_channels<'a>(
+    product: &FinalProduct,
+    channel_ids: &'a HashMap<String, Id>,
+) -> Vec<&'a Id> {
+    let mut channels: Vec<&Id> = channel_ids.get(&config().channel).into_iter().collect();
+    for channel_price in product.channel_prices() {
+        if let Some(channel_id) = channel_ids.get(&channel_price.channel) {
+            if !channels.contains(&channel_id) {
+                channels.push(channel_id);
+            }
+        }
+    }
+    channels
+}
+
+/// How a product shows up in its channels
+struct Listing {
+    is_published: bool,
+    visible_in_listings: bool,
+    is_available_for_purchase: bool,
+    /// The old creation date, so the storefront sorts the newest products like the old shop did
+    since: Option<DateTime>,
+}
+
+impl Listing {
+    /// Only when every old product of the family has `ended`, one still sold keeps it all listed
+    fn of(product: &FinalProduct) -> Self {
+        let ended = std::iter::once(product)
+            .chain(product.siblings.iter())
+            .all(|variant| variant.product.status() == Status::Ended);
+        match (ended, config().availability.ended) {
+            (false, _) => Listing {
+                is_published: true,
+                visible_in_listings: true,
+                is_available_for_purchase: true,
+                since: product.created_at.clone().map(DateTime),
+            },
+            (true, EndedProducts::Hidden) => Listing {
+                is_published: true,
+                visible_in_listings: false,
+                is_available_for_purchase: false,
+                since: product.created_at.clone().map(DateTime),
+            },
+            (true, EndedProducts::Unpublished) => Listing {
+                is_published: false,
+                visible_in_listings: false,
+                is_available_for_purchase: false,
+                since: None,
+            },
+        }
+    }
+
+    fn published_at(&self) -> Option<DateTime> {
+        self.since.clone().filter(|_| self.is_published)
+    }
+
+    /// Saleor refuses a date on something that isn't for sale
+    fn available_for_purchase_at(&self) -> Option<DateTime> {
+        self.since
+            .clone()
+            .filter(|_| self.is_available_for_purchase)
+    }
+}
+
+/// `arrival` products are on their way, they can be ordered ahead without a limit or an end date
+fn preorder(product: &FinalProduct) -> Option<PreorderSettingsInput> {
+    (product.product.status() == Status::Arrival).then_some(PreorderSettingsInput {
+        global_threshold: None,
+        end_date: None,
+    })
+}
+
+/// Old created and updated dates, saleor's own `created` is the day of the migration
+fn date_metadata(product: &FinalProduct) -> Vec<MetadataInput> {
+    let mut metadata = vec![];
+    if let Some(created_at) = &product.created_at {
+        metadata.push(MetadataInput {
+            key: "old_created_at",
+            value: created_at.as_str(),
+        });
+    }
+    if let Some(updated_at) = &product.updated_at {
+        metadata.push(MetadataInput {
+            key: "old_updated_at",
+            value: updated_at.as_str(),
+        });
+    }
+    metadata
+}
+
+/// Old availability text from `availability.texts`, `None` if there's none or it isn't mapped
+fn availability_metadata(product: &FinalProduct) -> Option<MetadataInput<'static>> {
+    let id = product.product.availability_text_id?;
+    let text = config().availability.texts.get(&i64::from(id))?;
+    Some(MetadataInput {
+        key: "availability",
+        value: text.as_str(),
+    })
+}
+
+pub fn product_channel_listing_update_operation<'a>(
+    product_saleor_id: &'a Id,
+    product: &FinalProduct,
+    channels: Vec<&'a Id>,
+) -> cynic::Operation<ProductChannelListingUpdate, ProductChannelListingUpdateVariables<'a>> {
+    let listing = Listing::of(product);
+    ProductChannelListingUpdate::build(ProductChannelListingUpdateVariables {
+        id: product_saleor_id,
+        input: ProductChannelListingUpdateInput {
+            update_channels: Some(
+                channels
+                    .into_iter()
+                    .map(|channel_id| ProductChannelListingAddInput {
+                        add_variants: None,
+                        remove_variants: None,
+                        available_for_purchase_at: listing.available_for_purchase_at(),
+                        published_at: listing.published_at(),
+                        available_for_purchase_date: None,
+                        publication_date: None,
+                        channel_id,
+                        is_available_for_purchase: Some(listing.is_available_for_purchase),
+                        is_published: Some(listing.is_published),
+                        visible_in_listings: Some(listing.visible_in_listings),
+                    })
+                    .collect(),
+            ),
+            remove_channels: None,
+        },
+    })
+}
+
+pub async fn product_channel_listing_update(
+    product_saleor_id: &Id,
+    product: &FinalProduct,
+    channel_ids: &HashMap<String, Id>,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<ProductErrorCode>> {
+    //4.1 productChannelListingUpdate
+    let channel_listing_update_operation = product_channel_listing_update_operation(
+        product_saleor_id,
+        product,
+        listed_channels(product, channel_ids),
+    );
+    saleor.mutate(channel_listing_update_operation).await?;
+    Ok(())
+}
+
+pub fn variant_create_operation<'a>(
+    saleor_product_id: &'a Id,
+    product: &'a FinalProduct,
+    warehouse_id: &'a Id,
+    attributes: &'a AttributeIds,
+) -> cynic::Operation<VariantCreate, VariantCreateVariables<'a>> {
+    let mut stocks: Option<Vec<StockInput>> = None;
+    if let Some(q) = product.product.quantity {
+        stocks = Some(vec![StockInput {
+            warehouse: warehouse_id,
+            quantity: q,
+        }]);
+    };
+    let mut variant_attributes = vec![];
+    if let (Some(attribute), Some(value)) = (&attributes.variant, &product.variant_value) {
+        variant_attributes.push(AttributeValueInput {
+            id: Some(attribute),
+            values: Some(vec![value.as_str()]),
+            ..Default::default()
+        });
+    }
+    VariantCreate::build(VariantCreateVariables {
+        input: ProductVariantCreateInput {
+            product: saleor_product_id,
+            sku: Some(&product.SKU),
+            external_reference: None,
+            name: product.variant_value.as_deref(),
+            attributes: variant_attributes,
+            metadata: availability_metadata(product).map(|m| vec![m]),
+            private_metadata: None,
+            preorder: preorder(product),
+            quantity_limit_per_customer: None,
+            stocks,
+            track_inventory: Some(true),
+            weight: None,
+        },
+    })
+}
+
+pub async fn variant_create(
+    saleor_product_id: &Id,
+    product: &FinalProduct,
+    warehouse_id: &Id,
+    attributes: &AttributeIds,
+    saleor: &Saleor,
+) -> Result<cynic::Id, SaleorError<ProductErrorCode>> {
+    let variant_create_operation =
+        variant_create_operation(saleor_product_id, product, warehouse_id, attributes);
+
+    Ok(saleor
+        .mutate(variant_create_operation)
+        .await?
+        .product_variant_create
+        .and_then(|c| c.product_variant)
+        .ok_or(SaleorError::NoData)?
+        .id)
+}
+
+fn positive_decimal(price: &str) -> PositiveDecimal {
+    PositiveDecimal(
+        Decimal::from_str(price)
+            .unwrap_or(Decimal::new(0, 2))
+            .round_dp(2),
+    )
+}
+
+/// One listing per channel the product has a price in, channels missing in saleor are skipped
+pub fn variant_listings<'a>(
+    product: &FinalProduct,
+    channel_ids: &'a HashMap<String, Id>,
+) -> Vec<ProductVariantChannelListingAddInput<'a>> {
+    product
+        .channel_prices()
+        .into_iter()
+        .filter_map(|channel_price| {
+            let Some(channel_id) = channel_ids.get(&channel_price.channel) else {
+                println!(
+                    "product '{}' has a price in unknown channel {}",
+                    product.product.id, channel_price.channel
+                );
+                return None;
+            };
+            Some(ProductVariantChannelListingAddInput {
+                channel_id,
+                price: positive_decimal(&channel_price.price),
+                cost_price: channel_price.cost_price.as_deref().map(positive_decimal),
+                preorder_threshold: None,
+            })
+        })
+        .collect()
+}
+
+pub fn variant_listing_update_operation<'a>(
+    variant_id: &'a Id,
+    listings: Vec<ProductVariantChannelListingAddInput<'a>>,
+) -> cynic::Operation<VariantChannelListingUpdate, VariantChannelListingUpdateVariables<'a>> {
+    VariantChannelListingUpdate::build(VariantChannelListingUpdateVariables {
+        id: variant_id,
+        input: listings,
+    })
+}
+
+pub async fn variant_listing_update(
+    product: &FinalProduct,
+    variant_id: &Id,
+    channel_ids: &HashMap<String, Id>,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<ProductErrorCode>> {
+    let listings = variant_listings(product, channel_ids);
+    if listings.is_empty() {
+        println!(
+            "product '{}' has no prices, not listing it",
+            product.product.id
+        );
+        return Ok(());
+    }
+    saleor
+        .mutate(variant_listing_update_operation(variant_id, listings))
+        .await?;
+    Ok(())
+}
+
+/// `image` is relative to `media_dir()`. With multipart uploads the file goes next to the operation
+/// into `variables.input.image`, see `product_media_upload`
+pub fn product_media_create_operation<'a>(
+    saleor_product_id: &'a Id,
+    product: &'a FinalProduct,
+    image: &str,
+) -> cynic::Operation<ProductMediaCreate, ProductMediaCreateVariables<'a>> {
+    let media_url = match config().media_upload {
+        MediaUpload::Url => Some(media_server::url(image)),
+        MediaUpload::Multipart => None,
+    };
+    ProductMediaCreate::build(ProductMediaCreateVariables {
+        input: ProductMediaCreateInput {
+            alt: Some(product.product.name.as_str()),
+            image: None,
+            product: saleor_product_id,
+            media_url,
+        },
+    })
+}
+
+pub fn product_media_upload(image: &str) -> std::io::Result<FileUpload> {
+    FileUpload::read("variables.input.image", image)
+}
+
+/// Why a single picture didn't make it
+#[derive(Debug)]
+pub enum MediaFailure {
+    /// The file couldn't be read for the multipart upload
+    Read(String),
+    Saleor(SaleorError<ProductErrorCode>),
+}
+
+/// Pictures `product_media_create` got through, and the ones it didn't
+#[derive(Default, Debug)]
+pub struct CreatedMedia {
+    pub created: Vec<(String, cynic::Id)>,
+    pub failed: Vec<(String, MediaFailure)>,
+}
+
+/// One productMediaCreate per picture. A refused or unreadable picture doesn't stop the rest,
+/// only a saleor that's gone altogether does
+pub async fn product_media_create(
+    saleor_product_id: &Id,
+    product: &FinalProduct,
+    images: &[String],
+    saleor: &Saleor,
+) -> Result<CreatedMedia, SaleorError<ProductErrorCode>> {
+    let mut media = CreatedMedia::default();
+    for image in images {
+        let media_create_operation =
+            product_media_create_operation(saleor_product_id, product, image);
+        let media_create_response = match config().media_upload {
+            MediaUpload::Url => saleor.mutate(media_create_operation).await,
+            MediaUpload::Multipart => match product_media_upload(image) {
+                Ok(file) => saleor.mutate_with_file(media_create_operation, &file).await,
+                Err(e) => {
+                    let failure = MediaFailure::Read(format!("{:?}", e));
+                    media.failed.push((image.clone(), failure));
+                    continue;
+                }
+            },
+        };
+        match media_create_response {
+            Ok(data) => match data.product_media_create.and_then(|c| c.media) {
+                Some(created) => media.created.push((image.clone(), created.id)),
+                None => {
+                    let failure = MediaFailure::Saleor(SaleorError::NoData);
+                    media.failed.push((image.clone(), failure));
+                }
+            },
+            Err(e @ SaleorError::Mutation(_)) => {
+                media.failed.push((image.clone(), MediaFailure::Saleor(e)))
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(media)
+}
+
+pub async fn variant_media_assign(
+    variant_id: &Id,
+    media_id: &Id,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<ProductErrorCode>> {
+    let variant_media_assign_operation = VariantMediaAssign::build(VariantMediaAssignVariables {
+        variant_id,
+        media_id,
+    });
+    saleor.mutate(variant_media_assign_operation).await?;
+    Ok(())
+}
+
+/// Every category that already carries our `old_id` metadata, keyed by that old id
+pub async fn existing_categories(
+    saleor: &Saleor,
+) -> anyhow::Result<HashMap<u32, ExistingCategory>> {
+    let mut existing = HashMap::new();
+    let mut after: Option<String> = None;
+    loop {
+        let existing_categories_operation = ExistingCategories::build(ExistingObjectsVariables {
+            after: after.as_deref(),
+        });
+        let response = saleor
+            .query(existing_categories_operation)
+            .await
+            .context("failed querying existing categories")?;
+        let Some(connection) = response.categories else {
+            break;
+        };
+        for edge in connection.edges {
+            if let Some(old_id) = edge
+                .node
+                .metafield
+                .as_ref()
+                .and_then(|id| id.parse::<u32>().ok())
+            {
+                existing.insert(old_id, edge.node);
+            }
+        }
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+    Ok(existing)
+}
+
+/// Every product that already carries our `old_id` metadata, keyed by that old id
+pub async fn existing_products(saleor: &Saleor) -> anyhow::Result<HashMap<u32, ExistingProduct>> {
+    let mut existing = HashMap::new();
+    let mut after: Option<String> = None;
+    loop {
+        let existing_products_operation = ExistingProducts::build(ExistingObjectsVariables {
+            after: after.as_deref(),
+        });
+        let response = saleor
+            .query(existing_products_operation)
+            .await
+            .context("failed querying existing products")?;
+        let Some(connection) = response.products else {
+            break;
+        };
+        for edge in connection.edges {
+            if let Some(old_id) = edge
+                .node
+                .metafield
+                .as_ref()
+                .and_then(|id| id.parse::<u32>().ok())
+            {
+                existing.insert(old_id, edge.node);
+            }
+        }
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+    Ok(existing)
+}
+
+pub async fn update_product(
+    saleor_id: &Id,
+    category_id: Option<&Id>,
+    product: &FinalProduct,
+    tax_class_id: &Id,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<ProductErrorCode>> {
+    let description = Jsonstring::from_string(product.product.description.clone());
+    let weight = product.product.weight.map(|w| WeightScalar(w.to_string()));
+    let old_id = product.product.id.to_string();
+    let mut metadata = vec![
+        MetadataInput {
+            key: "short_description",
+            value: product.product.short_description.as_str(),
+        },
+        MetadataInput {
+            key: "old_id",
+            value: &old_id,
+        },
+    ];
+    metadata.extend(availability_metadata(product));
+    metadata.extend(date_metadata(product));
+    let update_product_operation = ProductUpdate::build(ProductUpdateVariables {
+        id: saleor_id,
+        input: ProductInput {
+            attributes: None,
+            category: category_id,
+            charge_taxes: Some(true),
+            collections: None,
+            description: Some(description),
+            name: Some(product.product.name.as_str()),
+            // It keeps the slug saleor has, a suffixed one would only hit Unique again
+            slug: None,
+            tax_class: Some(tax_class_id),
+            tax_code: None,
+            seo: Some(product_seo(&product.product)),
+            weight,
+            rating: None,
+            metadata: Some(metadata),
+            private_metadata: None,
+            external_reference: None,
+        },
+    });
+
+    saleor.mutate(update_product_operation).await?;
+    Ok(())
+}
+
+/// Saleor ids of the channel, warehouse and tax class every product gets assigned to
+pub struct ShopIds {
+    pub channel_id: cynic::Id,
+    /// Main channel and every channel from `price_channels`, by slug
+    pub channel_ids: HashMap<String, cynic::Id>,
+    pub warehouse_id: cynic::Id,
+    pub tax_class_id: cynic::Id,
+}
+
+/// Looks up the configured channel and warehouse by slug and the tax class by name. Missing ones
+/// are created if the config has a `create_missing` section, otherwise the run stops right here
+pub async fn resolve_shop_ids(saleor: &Saleor) -> anyhow::Result<ShopIds> {
+    let shop_setup = saleor
+        .query(ShopSetup::build(()))
+        .await
+        .context("failed querying channels, warehouses and tax classes")?;
+    let bootstrap = config().create_missing.as_ref();
+
+    let mut new_warehouse = false;
+    let warehouse_id = match shop_setup
+        .warehouses
+        .into_iter()
+        .flat_map(|w| w.edges)
+        .find(|e| e.node.slug == config().warehouse)
+    {
+        Some(edge) => edge.node.id,
+        None => {
+            let bootstrap = bootstrap.with_context(|| {
+                format!("warehouse '{}' doesn't exist in saleor", config().warehouse)
+            })?;
+            println!("Creating warehouse {}", config().warehouse);
+            let address = &bootstrap.warehouse_address;
+            let warehouse = saleor
+                .mutate(CreateWarehouse::build(CreateWarehouseVariables {
+                    input: WarehouseCreateInput {
+                        name: &bootstrap.warehouse_name,
+                        slug: Some(&config().warehouse),
+                        address: AddressInput {
+                            first_name: None,
+                            last_name: None,
+                            company_name: address.company_name.as_deref(),
+                            street_address_1: Some(&address.street_address_1),
+                            street_address_2: None,
+                            city: Some(&address.city),
+                            postal_code: Some(&address.postal_code),
+                            country: Some(address.country),
+                            phone: None,
+                        },
+                    },
+                }))
+                .await
+                .context("failed creating warehouse")?
+                .create_warehouse
+                .and_then(|c| c.warehouse)
+                .context("failed creating warehouse")?;
+            new_warehouse = true;
+            warehouse.id
+        }
+    };
+
+    let channels = shop_setup.channels.unwrap_or_default();
+    let channel_id = match channels.iter().find(|c| c.slug == config().channel) {
+        Some(channel) => {
+            // a fresh warehouse has to be in the channel, or the stock can't be sold there
+            if new_warehouse {
+                saleor
+                    .mutate(UpdateChannel::build(UpdateChannelVariables {
+                        id: &channel.id,
+                        input: ChannelUpdateInput {
+                            add_warehouses: Some(vec![&warehouse_id]),
+                        },
+                    }))
+                    .await
+                    .context("failed adding warehouse to channel")?;
+            }
+            channel.id.clone()
+        }
+        None => {
+            let bootstrap = bootstrap.with_context(|| {
+                format!("channel '{}' doesn't exist in saleor", config().channel)
+            })?;
+            println!("Creating channel {}", config().channel);
+            saleor
+                .mutate(CreateChannel::build(CreateChannelVariables {
+                    input: ChannelCreateInput {
+                        name: &bootstrap.channel_name,
+                        slug: &config().channel,
+                        currency_code: &bootstrap.currency_code,
+                        default_country: bootstrap.default_country,
+                        add_warehouses: Some(vec![&warehouse_id]),
+                    },
+                }))
+                .await
+                .context("failed creating channel")?
+                .channel_create
+                .and_then(|c| c.channel)
+                .context("failed creating channel")?
+                .id
+        }
+    };
+
+    let mut channel_ids = HashMap::from([(config().channel.clone(), channel_id.clone())]);
+    for price_channel in &config().price_channels {
+        if channel_ids.contains_key(&price_channel.channel) {
+            continue;
+        }
+        let channel = channels
+            .iter()
+            .find(|c| c.slug == price_channel.channel)
+            .with_context(|| {
+                format!(
+                    "channel '{}' doesn't exist in saleor",
+                    price_channel.channel
+                )
+            })?;
+        channel_ids.insert(channel.slug.clone(), channel.id.clone());
+    }
+
+    let tax_class_id = match shop_setup
+        .tax_classes
+        .into_iter()
+        .flat_map(|t| t.edges)
+        .find(|e| e.node.name == config().tax_class)
+    {
+        Some(edge) => edge.node.id,
+        None => {
+            bootstrap.with_context(|| {
+                format!("tax class '{}' doesn't exist in saleor", config().tax_class)
+            })?;
+            println!("Creating tax class {}", config().tax_class);
+            saleor
+                .mutate(CreateTaxClass::build(CreateTaxClassVariables {
+                    input: TaxClassCreateInput {
+                        name: &config().tax_class,
+                    },
+                }))
+                .await
+                .context("failed creating tax class")?
+                .tax_class_create
+                .and_then(|c| c.tax_class)
+                .context("failed creating tax class")?
+                .id
+        }
+    };
+
+    Ok(ShopIds {
+        channel_id,
+        channel_ids,
+        warehouse_id,
+        tax_class_id,
+    })
+}
+
+/// Saleor ids of the attributes products and their variants get values for
+#[derive(Default, Debug)]
+pub struct AttributeIds {
+    /// Dropdown the variants of a grouped product are told apart by
+    pub variant: Option<cynic::Id>,
+    /// Attributes from the `attributes` config, by slug
+    pub product: HashMap<String, (cynic::Id, AttributeInputTypeEnum)>,
+}
+
+pub fn attribute_create_operation<'a>(
+    name: &'a str,
+    slug: &'a str,
+    input_type: AttributeInputTypeEnum,
+) -> cynic::Operation<CreateAttribute, CreateAttributeVariables<'a>> {
+    CreateAttribute::build(CreateAttributeVariables {
+        input: AttributeCreateInput {
+            name,
+            slug: Some(slug),
+            type_: AttributeTypeEnum::ProductType,
+            input_type: Some(input_type),
+            value_required: Some(false),
+            visible_in_storefront: Some(true),
+        },
+    })
+}
+
+/// Looks up the attribute by slug, creates it if it doesn't exist yet. An existing one of another
+/// input type is an error, saleor would refuse every value sent for it
+pub async fn ensure_attribute(
+    name: &str,
+    slug: &str,
+    input_type: AttributeInputTypeEnum,
+    saleor: &Saleor,
+) -> anyhow::Result<cynic::Id> {
+    let attribute = saleor
+        .query(AttributeBySlug::build(AttributeBySlugVariables { slug }))
+        .await
+        .with_context(|| format!("failed querying attribute '{slug}'"))?
+        .attribute;
+    if let Some(attribute) = attribute {
+        match attribute.input_type {
+            Some(existing) if existing != input_type => anyhow::bail!(
+                "attribute '{slug}' already exists as {:?}, the config wants {:?}",
+                existing,
+                input_type
+            ),
+            _ => return Ok(attribute.id),
+        }
+    }
+    println!("Creating attribute {name}");
+    Ok(saleor
+        .mutate(attribute_create_operation(name, slug, input_type))
+        .await
+        .with_context(|| format!("failed creating attribute '{slug}'"))?
+        .attribute_create
+        .and_then(|c| c.attribute)
+        .with_context(|| format!("failed creating attribute '{slug}'"))?
+        .id)
+}
+
+/// Attributes of a product type, the variant one is used for variant selection. `None` if there's
+/// nothing to assign
+pub fn assign_attributes_operation<'a>(
+    product_type_id: &'a cynic::Id,
+    type_attributes: &[String],
+    attributes: &'a AttributeIds,
+) -> Option<cynic::Operation<AssignAttributes, AssignAttributesVariables<'a>>> {
+    let mut operations = vec![];
+    for slug in type_attributes {
+        match attributes.product.get(slug) {
+            Some((id, _)) => operations.push(ProductAttributeAssignInput {
+                id,
+                type_: ProductAttributeType::Product,
+                variant_selection: None,
+            }),
+            None => println!("attribute '{slug}' isn't in the config, skipping"),
+        }
+    }
+    if let Some(variant) = &attributes.variant {
+        operations.push(ProductAttributeAssignInput {
+            id: variant,
+            type_: ProductAttributeType::Variant,
+            variant_selection: Some(true),
+        });
+    }
+    if operations.is_empty() {
+        return None;
+    }
+    Some(AssignAttributes::build(AssignAttributesVariables {
+        product_type_id,
+        operations,
+    }))
+}
+
+/// Puts the attributes on a freshly created product type
+pub async fn assign_attributes(
+    product_type_id: &cynic::Id,
+    type_attributes: &[String],
+    attributes: &AttributeIds,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<ProductErrorCode>> {
+    let Some(assign_operation) =
+        assign_attributes_operation(product_type_id, type_attributes, attributes)
+    else {
+        return Ok(());
+    };
+    saleor.mutate(assign_operation).await?;
+    Ok(())
+}
+
+/// Everything the single product path sends in five requests per product, as one row of a
+/// productBulkCreate. Siblings become more variants of the same row
+pub fn product_bulk_create_input<'a>(
+    category_id: Option<&'a Id>,
+    product: &'a FinalProduct,
+    old_id: &'a str,
+    tax_class_id: &'a Id,
+    product_type_id: &'a Id,
+    type_attributes: &[String],
+    attributes: &'a AttributeIds,
+    warehouse_id: &'a Id,
+    channel_ids: &'a HashMap<String, Id>,
+) -> ProductBulkCreateInput<'a> {
+    let create = product_create_input(
+        category_id,
+        product,
+        old_id,
+        tax_class_id,
+        product_type_id,
+        type_attributes,
+        attributes,
+    );
+    let family: Vec<&FinalProduct> = std::iter::once(product)
+        .chain(product.siblings.iter())
+        .collect();
+    // In family order and every picture once, so the created media can be matched back to the
+    // variants. Files to upload don't fit into one bulk request, those go up one by one afterwards
+    let mut media: Vec<MediaInput> = vec![];
+    if config().media_upload == MediaUpload::Url {
+        let mut seen: Vec<&str> = vec![];
+        for variant in &family {
+            for image in &variant.images {
+                if seen.contains(&image.as_str()) {
+                    continue;
+                }
+                seen.push(image);
+                media.push(MediaInput {
+                    alt: Some(variant.product.name.as_str()),
+                    media_url: Some(media_server::url(image)),
+                });
+            }
+        }
+    }
+    let listing = Listing::of(product);
+    let channel_listings = listed_channels(product, channel_ids)
+        .into_iter()
+        .map(|channel_id| ProductChannelListingCreateInput {
+            channel_id,
+            is_published: Some(listing.is_published),
+            published_at: listing.published_at(),
+            visible_in_listings: Some(listing.visible_in_listings),
+            is_available_for_purchase: Some(listing.is_available_for_purchase),
+            available_for_purchase_at: listing.available_for_purchase_at(),
+        })
+        .collect();
+    let variants = family
+        .iter()
+        .map(|variant| variant_bulk_create_input(variant, warehouse_id, attributes, channel_ids))
+        .collect();
+    ProductBulkCreateInput {
+        attributes: create.attributes,
+        category: create.category,
+        description: create.description,
+        name: create.name,
+        slug: create.slug,
+        tax_class: create.tax_class,
+        seo: create.seo,
+        weight: create.weight,
+        metadata: create.metadata,
+        product_type: create.product_type,
+        media: (!media.is_empty()).then_some(media),
+        channel_listings: Some(channel_listings),
+        variants: Some(variants),
+    }
+}
+
+pub fn variant_bulk_create_input<'a>(
+    product: &'a FinalProduct,
+    warehouse_id: &'a Id,
+    attributes: &'a AttributeIds,
+    channel_ids: &'a HashMap<String, Id>,
+) -> ProductVariantBulkCreateInput<'a> {
+    let mut variant_attributes = vec![];
+    if let (Some(attribute), Some(value)) = (&attributes.variant, &product.variant_value) {
+        variant_attributes.push(BulkAttributeValueInput {
+            id: Some(attribute),
+            values: Some(vec![value.as_str()]),
+        });
+    }
+    let listings = variant_listings(product, channel_ids);
+    ProductVariantBulkCreateInput {
+        attributes: variant_attributes,
+        sku: Some(&product.SKU),
+        name: product.variant_value.as_deref(),
+        track_inventory: Some(true),
+        preorder: preorder(product),
+        metadata: availability_metadata(product).map(|m| vec![m]),
+        stocks: product.product.quantity.map(|quantity| {
+            vec![StockInput {
+                warehouse: warehouse_id,
+                quantity,
+            }]
+        }),
+        channel_listings: (!listings.is_empty()).then_some(listings),
+    }
+}
+
+pub fn product_bulk_create_operation<'a>(
+    products: Vec<ProductBulkCreateInput<'a>>,
+) -> cynic::Operation<ProductBulkCreate, ProductBulkCreateVariables<'a>> {
+    ProductBulkCreate::build(ProductBulkCreateVariables {
+        // a refused row doesn't take the rest of the batch down with it
+        error_policy: ErrorPolicyEnum::RejectFailedRows,
+        products,
+    })
+}
+
+/// One result per input row, in the same order
+pub async fn product_bulk_create(
+    products: Vec<ProductBulkCreateInput<'_>>,
+    saleor: &Saleor,
+) -> Result<Vec<ProductBulkResult>, SaleorError<ProductBulkCreateErrorCode>> {
+    Ok(saleor
+        .mutate(product_bulk_create_operation(products))
+        .await?
+        .product_bulk_create
+        .ok_or(SaleorError::NoData)?
+        .results)
+}
+
+pub fn file_upload_operation() -> cynic::Operation<UploadFile, UploadFileVariables> {
+    UploadFile::build(UploadFileVariables {
+        file: Upload(String::new()),
+    })
+}
+
+/// Url saleor keeps the uploaded file under
+pub async fn file_upload(
+    file: &FileUpload,
+    saleor: &Saleor,
+) -> Result<String, SaleorError<UploadErrorCode>> {
+    saleor
+        .mutate_with_file(file_upload_operation(), file)
+        .await?
+        .file_upload
+        .and_then(|f| f.uploaded_file)
+        .map(|f| f.url)
+        .ok_or(SaleorError::NoData)
+}
+
+pub fn update_metadata_operation<'a>(
+    id: &'a Id,
+    input: Vec<MetadataInput<'a>>,
+) -> cynic::Operation<UpdateMetadata, UpdateMetadataVariables<'a>> {
+    UpdateMetadata::build(UpdateMetadataVariables { id, input })
+}
+
+/// Sets (or overwrites) the given keys, the rest of the object's metadata stays
+pub async fn update_metadata(
+    id: &Id,
+    input: Vec<MetadataInput<'_>>,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<MetadataErrorCode>> {
+    saleor.mutate(update_metadata_operation(id, input)).await?;
+    Ok(())
+}
+
+/// Name, description and seo of one language, the description as Editor.js
+pub fn translation_input(translation: &Translation) -> TranslationInput {
+    TranslationInput {
+        seo_title: seo_text(&[Some(translation.name.as_str())], SEO_TITLE_MAX),
+        seo_description: seo_text(
+            &[
+                Some(translation.short_description.as_str()),
+                Some(translation.description.as_str()),
+            ],
+            SEO_DESCRIPTION_MAX,
+        ),
+        name: Some(translation.name.as_str()).filter(|name| !name.is_empty()),
+        description: Some(&translation.description)
+            .filter(|description| !description.is_empty())
+            .map(|description| Jsonstring::from_string(description.clone())),
+    }
+}
+
+pub fn product_translate_operation<'a>(
+    product_id: &'a Id,
+    translation: &'a Translation,
+) -> cynic::Operation<ProductTranslate, TranslateVariables<'a>> {
+    ProductTranslate::build(TranslateVariables {
+        id: product_id,
+        input: translation_input(translation),
+        language_code: translation.language,
+    })
+}
+
+pub fn category_translate_operation<'a>(
+    category_id: &'a Id,
+    translation: &'a Translation,
+) -> cynic::Operation<CategoryTranslate, TranslateVariables<'a>> {
+    CategoryTranslate::build(TranslateVariables {
+        id: category_id,
+        input: translation_input(translation),
+        language_code: translation.language,
+    })
+}
+
+pub async fn product_translate(
+    product_id: &Id,
+    translation: &Translation,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<TranslationErrorCode>> {
+    saleor
+        .mutate(product_translate_operation(product_id, translation))
+        .await?;
+    Ok(())
+}
+
+pub async fn category_translate(
+    category_id: &Id,
+    translation: &Translation,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<TranslationErrorCode>> {
+    saleor
+        .mutate(category_translate_operation(category_id, translation))
+        .await?;
+    Ok(())
+}
+
+/// Old address with the country it was validated with
+pub fn address_input(address: &UserAddress, country: CountryCode) -> AddressInput {
+    AddressInput {
+        first_name: non_empty(&address.first_name),
+        last_name: non_empty(&address.last_name),
+        company_name: non_empty(&address.company),
+        street_address_1: non_empty(&address.street),
+        street_address_2: None,
+        city: non_empty(&address.city),
+        postal_code: non_empty(&address.zip),
+        country: Some(country),
+        phone: non_empty(&address.phone),
+    }
+}
+
+/// Old optional columns are often just whitespace
+pub fn non_empty(text: &Option<String>) -> Option<&str> {
+    text.as_deref().map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// The same address goes in as both default billing and shipping one, the old shop didn't tell
+/// them apart
+pub fn customer_input<'a>(
+    customer: &'a FinalCustomer,
+    old_id: &'a str,
+    address: Option<(&'a UserAddress, CountryCode)>,
+) -> CustomerInput<'a> {
+    CustomerInput {
+        default_billing_address: address.map(|(address, country)| address_input(address, country)),
+        default_shipping_address: address.map(|(address, country)| address_input(address, country)),
+        first_name: non_empty(&customer.user.first_name),
+        last_name: non_empty(&customer.user.last_name),
+        email: Some(customer.email.as_str()),
+        is_active: Some(true),
+        metadata: Some(vec![MetadataInput {
+            key: "old_id",
+            value: old_id,
+        }]),
+    }
+}
+
+pub fn customer_create_operation(
+    input: CustomerInput,
+) -> cynic::Operation<CustomerCreate, CustomerCreateVariables> {
+    CustomerCreate::build(CustomerCreateVariables {
+        input: input.into(),
+    })
+}
+
+pub async fn customer_create(
+    input: CustomerInput<'_>,
+    saleor: &Saleor,
+) -> Result<Id, SaleorError<AccountErrorCode>> {
+    let response = saleor.mutate(customer_create_operation(input)).await?;
+    response
+        .customer_create
+        .and_then(|c| c.user)
+        .map(|user| user.id)
+        .ok_or(SaleorError::NoData)
+}
+
+pub fn customer_bulk_update_operation(
+    customers: Vec<CustomerBulkUpdateInput>,
+) -> cynic::Operation<CustomerBulkUpdate, CustomerBulkUpdateVariables> {
+    CustomerBulkUpdate::build(CustomerBulkUpdateVariables {
+        customers,
+        error_policy: ErrorPolicyEnum::RejectFailedRows,
+    })
+}
+
+/// One result per input row, in the same order
+pub async fn customer_bulk_update(
+    customers: Vec<CustomerBulkUpdateInput<'_>>,
+    saleor: &Saleor,
+) -> Result<Vec<CustomerBulkResult>, SaleorError<CustomerBulkUpdateErrorCode>> {
+    let response = saleor
+        .mutate(customer_bulk_update_operation(customers))
+        .await?;
+    response
+        .customer_bulk_update
+        .map(|bulk| bulk.results)
+        .ok_or(SaleorError::NoData)
+}
+
+pub async fn existing_customers(saleor: &Saleor) -> anyhow::Result<HashMap<u32, Id>> {
+    let mut existing = HashMap::new();
+    let mut after: Option<String> = None;
+    loop {
+        let existing_customers_operation = ExistingCustomers::build(ExistingObjectsVariables {
+            after: after.as_deref(),
+        });
+        let response = saleor
+            .query(existing_customers_operation)
+            .await
+            .context("failed querying existing customers")?;
+        let Some(connection) = response.customers else {
+            break;
+        };
+        for edge in connection.edges {
+            if let Some(old_id) = edge
+                .node
+                .metafield
+                .as_ref()
+                .and_then(|id| id.parse::<u32>().ok())
+            {
+                existing.insert(old_id, edge.node.id);
+            }
+        }
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+    Ok(existing)
+}
+
+/// Saleor's limit per orderBulkCreate
+pub const ORDER_BULK_LIMIT: usize = 50;
+
+/// Old price columns as saleor money, unparsable ones as 0
+fn old_decimal(value: &BigDecimal) -> Decimal {
+    Decimal::from_str(&value.to_string()).unwrap_or_default()
+}
+
+fn taxed_money(net: Decimal, gross: Decimal) -> TaxedMoneyInput {
+    TaxedMoneyInput {
+        gross: PositiveDecimal(gross.round_dp(2)),
+        net: PositiveDecimal(net.round_dp(2)),
+    }
+}
+
+/// The old `vat` is in percent, saleor wants a fraction. Without it, it's worked out of the prices
+fn tax_rate(vat: Option<&BigDecimal>, net: Decimal, gross: Decimal) -> Option<PositiveDecimal> {
+    let rate = match vat {
+        Some(vat) => old_decimal(vat) / Decimal::ONE_HUNDRED,
+        None if !net.is_zero() => (gross - net) / net,
+        None => return None,
+    };
+    Some(PositiveDecimal(rate.round_dp(4)))
+}
+
+/// A line and the variant it's sold as
+pub fn order_line_input<'a>(
+    line: &'a OrderLine,
+    variant_id: &'a Id,
+    created_at: &DateTime,
+    warehouse_id: &'a Id,
+) -> OrderBulkCreateOrderLineInput<'a> {
+    let quantity = Decimal::from(line.quantity);
+    let net = old_decimal(&line.price) * quantity;
+    let gross = old_decimal(&line.price_with_vat) * quantity;
+    OrderBulkCreateOrderLineInput {
+        variant_id,
+        product_name: Some(line.name.as_str()),
+        created_at: created_at.clone(),
+        is_shipping_required: true,
+        is_gift_card: false,
+        quantity: line.quantity as i32,
+        total_price: taxed_money(net, gross),
+        undiscounted_total_price: taxed_money(net, gross),
+        warehouse: warehouse_id,
+        tax_rate: tax_rate(line.vat.as_ref(), net, gross),
+    }
+}
+
+/// A past order with its original status and date, stock isn't touched
+pub fn order_bulk_create_input<'a>(
+    prepared: &'a PreparedOrder<'a>,
+    lines: Vec<OrderBulkCreateOrderLineInput<'a>>,
+    settings: &'a OrdersConfig,
+) -> OrderBulkCreateInput<'a> {
+    let order = prepared.order;
+    let status = settings
+        .statuses
+        .get(&order.order.status_id)
+        .copied()
+        .unwrap_or(settings.default_status);
+    let user = match &prepared.customer_id {
+        Some(id) => OrderBulkCreateUserInput {
+            id: Some(id),
+            email: None,
+        },
+        None => OrderBulkCreateUserInput {
+            id: None,
+            email: Some(order.order.email.as_str()),
+        },
+    };
+    let delivery_method = order.order.shipping_name.as_deref().map(|name| {
+        let net = order
+            .order
+            .shipping_price
+            .as_ref()
+            .map(old_decimal)
+            .unwrap_or_default();
+        let gross = order
+            .order
+            .shipping_price_with_vat
+            .as_ref()
+            .map(old_decimal)
+            .unwrap_or(net);
+        OrderBulkCreateDeliveryMethodInput {
+            shipping_method_name: Some(name),
+            shipping_price: Some(taxed_money(net, gross)),
+            shipping_tax_rate: tax_rate(None, net, gross),
+        }
+    });
+    let mut metadata = vec![
+        MetadataInput {
+            key: "old_id",
+            value: prepared.old_id.as_str(),
+        },
+        MetadataInput {
+            key: "old_number",
+            value: order.order.number.as_str(),
+        },
+    ];
+    if let Some(missing_lines) = &prepared.missing_lines {
+        metadata.push(MetadataInput {
+            key: "missing_lines",
+            value: missing_lines.as_str(),
+        });
+    }
+    OrderBulkCreateInput {
+        external_reference: Some(prepared.old_id.as_str()),
+        channel: config().channel.as_str(),
+        created_at: prepared.created_at.clone(),
+        status: Some(status),
+        user,
+        billing_address: address_input(&order.billing, prepared.billing_country),
+        shipping_address: prepared
+            .shipping
+            .map(|(address, country)| address_input(address, country)),
+        currency: settings.currency.as_str(),
+        metadata: Some(metadata),
+        customer_note: non_empty(&order.order.note),
+        language_code: settings.language_code,
+        display_gross_prices: Some(true),
+        lines,
+        delivery_method,
+    }
+}
+
+pub fn order_bulk_create_operation<'a>(
+    orders: Vec<OrderBulkCreateInput<'a>>,
+) -> cynic::Operation<OrderBulkCreate, OrderBulkCreateVariables<'a>> {
+    OrderBulkCreate::build(OrderBulkCreateVariables {
+        error_policy: ErrorPolicyEnum::RejectFailedRows,
+        orders,
+        // they're history, the stock they took is long gone
+        stock_update_policy: StockUpdatePolicyEnum::Skip,
+    })
+}
+
+/// One result per input row, in the same order
+pub async fn order_bulk_create(
+    orders: Vec<OrderBulkCreateInput<'_>>,
+    saleor: &Saleor,
+) -> Result<Vec<OrderBulkCreateResult>, SaleorError<OrderBulkCreateErrorCode>> {
+    let response = saleor.mutate(order_bulk_create_operation(orders)).await?;
+    response
+        .order_bulk_create
+        .map(|bulk| bulk.results)
+        .ok_or(SaleorError::NoData)
+}
+
+/// Percentage sale without anything in it yet, see `sale_channel_listing_update_operation` and
+/// `sale_catalogues_add_operation`
+pub fn sale_create_operation(name: &str) -> cynic::Operation<SaleCreate, SaleCreateVariables> {
+    SaleCreate::build(SaleCreateVariables {
+        input: SaleInput {
+            name: Some(name),
+            type_: Some(DiscountValueTypeEnum::Percentage),
+        },
+    })
+}
+
+pub async fn sale_create(
+    name: &str,
+    saleor: &Saleor,
+) -> Result<Id, SaleorError<DiscountErrorCode>> {
+    let response = saleor.mutate(sale_create_operation(name)).await?;
+    response
+        .sale_create
+        .and_then(|s| s.sale)
+        .map(|sale| sale.id)
+        .ok_or(SaleorError::NoData)
+}
+
+/// Every sale by name, the migration names its sales after the percentage
+pub async fn existing_sales(saleor: &Saleor) -> anyhow::Result<HashMap<String, Id>> {
+    let mut existing = HashMap::new();
+    let mut after: Option<String> = None;
+    loop {
+        let existing_sales_operation = ExistingSales::build(ExistingObjectsVariables {
+            after: after.as_deref(),
+        });
+        let response = saleor
+            .query(existing_sales_operation)
+            .await
+            .context("failed querying existing sales")?;
+        let Some(connection) = response.sales else {
+            break;
+        };
+        for edge in connection.edges {
+            existing.insert(edge.node.name, edge.node.id);
+        }
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+    Ok(existing)
+}
+
+/// The same percentage in every channel
+pub fn sale_channel_listing_update_operation<'a>(
+    sale_id: &'a Id,
+    channels: Vec<&'a Id>,
+    percentage: Decimal,
+) -> cynic::Operation<SaleChannelListingUpdate, SaleChannelListingUpdateVariables<'a>> {
+    SaleChannelListingUpdate::build(SaleChannelListingUpdateVariables {
+        id: sale_id,
+        input: SaleChannelListingInput {
+            add_channels: Some(
+                channels
+                    .into_iter()
+                    .map(|channel_id| SaleChannelListingAddInput {
+                        channel_id,
+                        discount_value: PositiveDecimal(percentage),
+                    })
+                    .collect(),
+            ),
+        },
+    })
+}
+
+pub async fn sale_channel_listing_update(
+    sale_id: &Id,
+    channels: Vec<&Id>,
+    percentage: Decimal,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<DiscountErrorCode>> {
+    saleor
+        .mutate(sale_channel_listing_update_operation(
+            sale_id, channels, percentage,
+        ))
+        .await?;
+    Ok(())
+}
+
+pub fn sale_catalogues_add_operation<'a>(
+    sale_id: &'a Id,
+    catalogue: CatalogueInput<'a>,
+) -> cynic::Operation<SaleCataloguesAdd, SaleCataloguesAddVariables<'a>> {
+    SaleCataloguesAdd::build(SaleCataloguesAddVariables {
+        id: sale_id,
+        input: catalogue,
+    })
+}
+
+pub async fn sale_catalogues_add(
+    sale_id: &Id,
+    catalogue: CatalogueInput<'_>,
+    saleor: &Saleor,
+) -> Result<(), SaleorError<DiscountErrorCode>> {
+    saleor
+        .mutate(sale_catalogues_add_operation(sale_id, catalogue))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_cuts_on_a_word_boundary() {
+        assert_eq!(truncate("short".to_owned(), 10), "short");
+        assert_eq!(truncate("one two three".to_owned(), 10), "one two…");
+        assert_eq!(truncate("one, two three".to_owned(), 9), "one…");
+        assert_eq!(truncate("onetwothree".to_owned(), 5), "onet…");
+        assert_eq!(truncate("anything".to_owned(), 1), "…");
+        assert_eq!(truncate("anything".to_owned(), 0), "");
+        assert_eq!(truncate(String::new(), 0), "");
+    }
+
+    #[test]
+    fn truncate_counts_characters_not_bytes() {
+        // 12 characters, 19 bytes
+        let text = "Žltý čierny".to_owned() + "ť";
+        assert_eq!(truncate(text.clone(), 12), text);
+        assert_eq!(truncate(text.clone(), 11), "Žltý…");
+        assert_eq!(truncate("ľščťžýáíé".to_owned(), 5), "ľščť…");
+    }
+
+    #[test]
+    fn plain_text_drops_markup_and_json() {
+        assert_eq!(
+            plain_text("<p>Tričko <b>z bavlny</b></p>\n<p>a&nbsp;ľanu &amp; viac</p>"),
+            "Tričko z bavlny a ľanu & viac"
+        );
+        assert_eq!(plain_text(r#"{"sk":"<p>Košeľa</p>"}"#), "Košeľa");
+        assert_eq!(plain_text("<br> <p></p>"), "");
+    }
+
+    #[test]
+    fn seo_text_takes_the_first_candidate_with_text() {
+        let description = "Veľmi ".repeat(100);
+        assert_eq!(
+            seo_text(&[None, Some("<p> </p>"), Some("Čiapka")], SEO_TITLE_MAX),
+            Some("Čiapka".to_owned())
+        );
+        assert_eq!(seo_text(&[Some(""), None], SEO_TITLE_MAX), None);
+        let cut = seo_text(&[Some(description.as_str())], SEO_DESCRIPTION_MAX).unwrap();
+        // 49 whole words and the ellipsis, the 50th doesn't fit
+        assert_eq!(cut, "Veľmi ".repeat(49).trim_end().to_owned() + "…");
+    }
+
+    #[test]
+    fn tax_rate_from_vat_or_prices() {
+        let decimal = |text| Decimal::from_str(text).unwrap();
+        let rate = |vat: Option<&str>, net, gross| {
+            let vat = vat.map(|vat| BigDecimal::from_str(vat).unwrap());
+            tax_rate(vat.as_ref(), decimal(net), decimal(gross)).map(|rate| rate.0)
+        };
+        assert_eq!(rate(Some("20"), "10", "12"), Some(decimal("0.2")));
+        // the old vat wins over the prices
+        assert_eq!(rate(Some("10.00"), "10", "12"), Some(decimal("0.1")));
+        assert_eq!(rate(Some("0"), "10", "10"), Some(Decimal::ZERO));
+        assert_eq!(rate(None, "10", "12"), Some(decimal("0.2")));
+        assert_eq!(rate(None, "3", "3.70"), Some(decimal("0.2333")));
+        assert_eq!(rate(None, "0", "0"), None);
     }
-    Err(SaleorGraphqlError::Other(ProductErrorCode::GraphqlError))
 }
@@ -0,0 +1,262 @@
+use crate::config::{config, ImagePreparation};
+use crate::get_sqls::QueryAllResult;
+use anyhow::Context;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Re-encodes every product and category picture into `image_preparation.output` and points the
+/// products and categories at the results. The real format is sniffed from the bytes, anything
+/// that isn't a GIF becomes a JPEG (or PNG if it has transparency) without EXIF and no bigger than
+/// `max_dimension`. Files are named by content hash, so a picture shared by many products is only
+/// prepared once and the same one twice on a product is only uploaded once
+pub fn prepare_images(data: &mut QueryAllResult) -> anyhow::Result<()> {
+    let Some(settings) = &config().image_preparation else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&settings.output).with_context(|| {
+        format!(
+            "Failed to create prepared media folder {}",
+            settings.output.display()
+        )
+    })?;
+    println!("Preparing pictures into {}...", settings.output.display());
+    // source file name → prepared file name, None if it can't be used
+    let mut prepared: HashMap<String, Option<String>> = HashMap::new();
+    let mut prepare = |source: &str| -> Option<String> {
+        prepared
+            .entry(source.to_owned())
+            .or_insert_with(
+                || match prepare_image(&config().media_root.join(source), settings) {
+                    Ok(name) => Some(name),
+                    Err(e) => {
+                        println!("picture '{}' left out: {:?}", source, e);
+                        None
+                    }
+                },
+            )
+            .clone()
+    };
+
+    for product in data.products.iter_mut() {
+        prepare_all(&mut product.images, &mut prepare);
+        for sibling in product.siblings.iter_mut() {
+            prepare_all(&mut sibling.images, &mut prepare);
+        }
+    }
+    for category in &data.categories {
+        let mut category = category.write();
+        if let Some(image) = category.image.take() {
+            category.image = prepare(&image);
+        }
+    }
+
+    let unique: HashSet<&String> = prepared.values().flatten().collect();
+    println!(
+        "Prepared {} pictures into {} files, {} left out",
+        prepared.len(),
+        unique.len(),
+        prepared.values().filter(|p| p.is_none()).count()
+    );
+    Ok(())
+}
+
+/// Swaps the product's pictures for the prepared ones, a picture that's on it twice stays once
+fn prepare_all(images: &mut Vec<String>, prepare: &mut impl FnMut(&str) -> Option<String>) {
+    let mut prepared: Vec<String> = vec![];
+    for image in images.drain(..) {
+        if let Some(image) = prepare(&image) {
+            if !prepared.contains(&image) {
+                prepared.push(image);
+            }
+        }
+    }
+    *images = prepared;
+}
+
+/// Name of the prepared file in `settings.output`, already prepared ones from earlier runs are
+/// reused
+fn prepare_image(source: &Path, settings: &ImagePreparation) -> anyhow::Result<String> {
+    let bytes = std::fs::read(source)?;
+    let format = image::guess_format(&bytes).context("not a picture")?;
+    // The settings go into the hash too, changing them prepares everything again
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    hasher.update(settings.max_dimension.to_le_bytes());
+    hasher.update([settings.jpeg_quality]);
+    let hash: String = hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    // GIFs can be animated, re-encoding would keep only the first frame
+    if format == ImageFormat::Gif {
+        let name = format!("{}.gif", hash);
+        let path = settings.output.join(&name);
+        if !path.is_file() {
+            std::fs::write(path, &bytes)?;
+        }
+        return Ok(name);
+    }
+
+    let mut decoder = ImageReader::with_format(Cursor::new(&bytes), format).into_decoder()?;
+    let extension = match decoder.color_type().has_alpha() {
+        true => "png",
+        false => "jpg",
+    };
+    let name = format!("{}.{}", hash, extension);
+    let path = settings.output.join(&name);
+    if path.is_file() {
+        return Ok(name);
+    }
+    let orientation = decoder.orientation()?;
+    let mut picture = DynamicImage::from_decoder(decoder)?;
+    // EXIF doesn't survive re-encoding, so the rotation it asks for goes into the pixels
+    picture.apply_orientation(orientation);
+    if picture.width().max(picture.height()) > settings.max_dimension {
+        picture = picture.resize(
+            settings.max_dimension,
+            settings.max_dimension,
+            FilterType::Lanczos3,
+        );
+    }
+
+    let mut out = Cursor::new(Vec::new());
+    match extension {
+        "png" => picture.write_to(&mut out, ImageFormat::Png)?,
+        _ => DynamicImage::ImageRgb8(picture.to_rgb8()).write_with_encoder(
+            JpegEncoder::new_with_quality(&mut out, settings.jpeg_quality),
+        )?,
+    }
+    std::fs::write(path, out.into_inner())?;
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb, RgbImage, Rgba, RgbaImage};
+
+    /// Empty folder with the sources in it and `prepared` for the output
+    fn folder(name: &str) -> (std::path::PathBuf, ImagePreparation) {
+        let dir = std::env::temp_dir().join(format!("images-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let output = dir.join("prepared");
+        std::fs::create_dir_all(&output).unwrap();
+        let settings = ImagePreparation {
+            output,
+            max_dimension: 100,
+            jpeg_quality: 85,
+        };
+        (dir, settings)
+    }
+
+    fn write(picture: DynamicImage, format: ImageFormat, path: &Path) {
+        picture.save_with_format(path, format).unwrap();
+    }
+
+    #[test]
+    fn format_comes_from_the_bytes() {
+        let (dir, settings) = folder("format");
+        let opaque = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 4, Rgb([200, 0, 0])));
+        let transparent =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([0, 0, 200, 100])));
+        // the extensions lie on purpose
+        write(opaque.clone(), ImageFormat::Png, &dir.join("opaque.gif"));
+        write(transparent, ImageFormat::Png, &dir.join("transparent.jpg"));
+        write(opaque, ImageFormat::Gif, &dir.join("animated.png"));
+        std::fs::write(dir.join("notes.jpg"), b"not a picture").unwrap();
+
+        let opaque = prepare_image(&dir.join("opaque.gif"), &settings).unwrap();
+        assert!(opaque.ends_with(".jpg"));
+        let bytes = std::fs::read(settings.output.join(&opaque)).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+
+        let transparent = prepare_image(&dir.join("transparent.jpg"), &settings).unwrap();
+        assert!(transparent.ends_with(".png"));
+        let bytes = std::fs::read(settings.output.join(&transparent)).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+
+        // gifs are copied as they are
+        let gif = prepare_image(&dir.join("animated.png"), &settings).unwrap();
+        assert!(gif.ends_with(".gif"));
+        assert_eq!(
+            std::fs::read(settings.output.join(&gif)).unwrap(),
+            std::fs::read(dir.join("animated.png")).unwrap()
+        );
+
+        assert!(prepare_image(&dir.join("notes.jpg"), &settings).is_err());
+        assert!(prepare_image(&dir.join("missing.jpg"), &settings).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_big_pictures_shrink() {
+        let (dir, settings) = folder("resize");
+        let wide = DynamicImage::ImageRgb8(RgbImage::new(300, 150));
+        let tall = DynamicImage::ImageRgb8(RgbImage::new(50, 400));
+        let small = DynamicImage::ImageRgb8(RgbImage::new(80, 60));
+        write(wide, ImageFormat::Png, &dir.join("wide.png"));
+        write(tall, ImageFormat::Png, &dir.join("tall.png"));
+        write(small, ImageFormat::Png, &dir.join("small.png"));
+
+        let dimensions = |source: &str| {
+            let name = prepare_image(&dir.join(source), &settings).unwrap();
+            image::open(settings.output.join(name))
+                .unwrap()
+                .dimensions()
+        };
+        assert_eq!(dimensions("wide.png"), (100, 50));
+        assert_eq!(dimensions("tall.png"), (13, 100));
+        assert_eq!(dimensions("small.png"), (80, 60));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn same_bytes_are_prepared_once() {
+        let (dir, settings) = folder("dedupe");
+        let picture = DynamicImage::ImageRgb8(RgbImage::from_pixel(20, 10, Rgb([0, 150, 0])));
+        write(picture, ImageFormat::Png, &dir.join("a.png"));
+        std::fs::copy(dir.join("a.png"), dir.join("copy of a.png")).unwrap();
+
+        let first = prepare_image(&dir.join("a.png"), &settings).unwrap();
+        assert_eq!(
+            prepare_image(&dir.join("copy of a.png"), &settings).unwrap(),
+            first
+        );
+        // one prepared by an earlier run is left alone
+        std::fs::write(settings.output.join(&first), b"earlier run").unwrap();
+        assert_eq!(prepare_image(&dir.join("a.png"), &settings).unwrap(), first);
+        assert_eq!(
+            std::fs::read(settings.output.join(&first)).unwrap(),
+            b"earlier run"
+        );
+        // other settings, other file
+        let settings = ImagePreparation {
+            max_dimension: 10,
+            ..settings
+        };
+        assert_ne!(prepare_image(&dir.join("a.png"), &settings).unwrap(), first);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pictures_on_a_product_twice_stay_once() {
+        let mut images = vec![
+            "a.jpg".to_owned(),
+            "broken.jpg".to_owned(),
+            "copy of a.jpg".to_owned(),
+            "b.jpg".to_owned(),
+        ];
+        prepare_all(&mut images, &mut |source| match source {
+            "broken.jpg" => None,
+            "b.jpg" => Some("2.jpg".to_owned()),
+            _ => Some("1.jpg".to_owned()),
+        });
+        assert_eq!(images, ["1.jpg", "2.jpg"]);
+    }
+}
@@ -1,64 +1,179 @@
 #![allow(non_snake_case)]
 
+mod config;
+mod customers;
+mod discounts;
+mod dry_run;
+mod editorjs;
 mod get_sqls;
 mod gql_queries;
+mod images;
+mod media_server;
+mod orders;
+mod pipeline;
+mod redirects;
+mod report;
+mod saleor;
+mod state;
 mod tests;
 
 use anyhow::Context;
+use clap::{Parser, Subcommand};
+use config::{config, MediaUpload};
 use cynic::MutationBuilder;
-use cynic::{http::SurfExt, GraphQlResponse};
-use dotenvy_macro::dotenv;
-use get_sqls::query_all;
-use gql_queries::{product_channel_listing_update, CreateTokenVariables, GQL_Endpoint};
-use reqwest::multipart::{Form, Part};
+use get_sqls::{query_all, query_customers, query_orders};
+use parking_lot::{Mutex, RwLock};
 
-use std::io::prelude::*;
-use std::rc::Rc;
-use std::{cell::RefCell, fs::File};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::get_sqls::FinalProductType;
 use crate::gql_queries::{
-    create_product, create_product_type, product_media_create, variant_create,
-    variant_listing_update, variant_media_assign, CreateCategory, MetadataInput,
-    Porudct_Tax_Class_ID, ProductErrorCode, Product_Channel_ID, Product_Warehouse_ID,
-    SaleorGraphqlError,
+    assign_attributes, category_translate, create_product_type, ensure_attribute,
+    existing_categories, existing_customers, existing_products, existing_sales, resolve_shop_ids,
+    AttributeIds, AttributeInputTypeEnum,
 };
+use crate::media_server::MediaServer;
+use crate::pipeline::ProductPipeline;
+use crate::report::{Outcome, Report};
+use crate::saleor::{FileUpload, Saleor};
+use crate::state::{MigrationState, OldKey, Slugs, Step};
+
+/// Migrates the old Elias shop MariaDB into Saleor
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// Yaml file with endpoints, credentials and saleor ids, see migration.example.yaml
+    #[arg(short, long, default_value = config::CONFIG_FILE)]
+    config: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Upload all categories and products to saleor, customers, orders and discounts too if their
+    /// sections are set
+    Migrate {
+        /// Skip the steps a previous run already recorded in the state file
+        #[arg(long)]
+        resume: bool,
+        /// Update objects that already carry an old_id instead of creating duplicates
+        #[arg(long)]
+        sync: bool,
+    },
+    /// Write every planned mutation into an ndjson file, saleor isn't touched at all
+    DryRun {
+        #[arg(default_value = dry_run::DRY_RUN_FILE)]
+        output: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    //std::env::set_var("RUST_BACKTRACE", "1");
-    /*jä
-    use tokio::process::Command;
-    Command::new("npm")
-        .args(["run", "start"])
-        //might wanna replace this if not running on my mashine lol
-        .current_dir(r"/home/djkato/code/db-migration/media")
-        .kill_on_drop(true)
-        .spawn()
-        .expect("Failed to serve media");
-    println!("Started media serve process");
-    //Start the media server so it can serve images
-    //   tokio::spawn(async move {
-    */
+    let cli = Cli::parse();
+    config::init(&cli.config)?;
+    match cli.command {
+        Command::Migrate { resume, sync } => migrate(resume, sync).await,
+        Command::DryRun { output } => {
+            println!("Querying all data from Old db...");
+            let data = query_all().await?;
+            let customers = match config().customers {
+                Some(_) => query_customers().await?,
+                None => vec![],
+            };
+            let orders = match config().orders {
+                Some(_) => query_orders().await?,
+                None => vec![],
+            };
+            dry_run::dry_run(&data, &customers, &orders, &output)
+        }
+    }
+}
 
-    // });
+async fn migrate(resume: bool, sync: bool) -> anyhow::Result<()> {
+    // Written even if the run dies halfway, that's when it's needed the most
+    let report = Report::default();
+    let result = migrate_all(resume, sync, &report).await;
+    report.write(report::REPORT_JSON, report::REPORT_MD)?;
+    result
+}
+
+async fn migrate_all(resume: bool, sync: bool, report: &Report) -> anyhow::Result<()> {
+    //std::env::set_var("RUST_BACKTRACE", "1");
+    // Saleor downloads product pictures from it, stops when the run ends or fails
+    let _media_server = match config().media_upload {
+        MediaUpload::Url => Some(MediaServer::start().await?),
+        MediaUpload::Multipart => None,
+    };
     let dec = rust_decimal::Decimal::new(200, 2);
-    // To write errors to
-    let mut log_file = std::fs::OpenOptions::new()
-        .append(true)
-        .write(true)
-        .create(true)
-        .open("errors.log")?;
+    // Steps that already went through, so a crashed run can continue with --resume
+    let mut state = MigrationState::open(state::STATE_FILE, resume)?;
 
     println!("Querying all data from Old db...");
-    let data = query_all().await?;
+    let mut data = query_all().await?;
+    images::prepare_images(&mut data)?;
     let categories = data.categories;
     let mut products = data.products;
-    let _product_types = data.product_types;
+    let product_types = data.product_types;
     println!("Success!");
-    let (mut client, mut jwt) = saleor_login().await?;
-    //INFO: MAGIC NUMBER!
-    let pure_jwt = jwt.split_at(7).1;
-    let tax_class_id = cynic::Id::new(gql_queries::Porudct_Tax_Class_ID);
+    let old_urls = redirects::OldUrls::collect(&categories, &products, &state);
+    for product in products
+        .iter()
+        .flat_map(|p| std::iter::once(p).chain(&p.siblings))
+    {
+        // every SKU gets " 001", anything higher means its code was taken
+        let base_sku = format!("{} 001", product.product.code);
+        if product.SKU != base_sku {
+            report.renamed(
+                "sku",
+                product.product.id,
+                &product.product.name,
+                &product.product.code,
+                &product.SKU,
+            );
+        }
+    }
+    let saleor = Saleor::login().await?;
+    // Objects a previous run already created get updated instead of duplicated
+    let (existing_saleor_categories, existing_saleor_products) = match sync {
+        true => {
+            println!("Looking up categories and products that already have an old_id...");
+            (
+                existing_categories(&saleor).await?,
+                existing_products(&saleor).await?,
+            )
+        }
+        false => (HashMap::new(), HashMap::new()),
+    };
+    println!("Looking up channel, warehouse and tax class...");
+    let shop_ids = resolve_shop_ids(&saleor).await?;
+    let tax_class_id = shop_ids.tax_class_id;
+    let mut attribute_ids = AttributeIds::default();
+    if let Some(grouping) = &config().variant_grouping {
+        attribute_ids.variant = Some(
+            ensure_attribute(
+                &grouping.attribute_name,
+                &grouping.attribute_slug,
+                AttributeInputTypeEnum::Dropdown,
+                &saleor,
+            )
+            .await?,
+        );
+    }
+    for attribute in &config().attributes {
+        let id = ensure_attribute(
+            &attribute.name,
+            &attribute.slug,
+            attribute.input_type,
+            &saleor,
+        )
+        .await?;
+        attribute_ids
+            .product
+            .insert(attribute.slug.clone(), (id, attribute.input_type));
+    }
     /*
     Strategy:
     keep on checking if signature has expired, if so re-fresh signature and continue
@@ -72,458 +187,348 @@ async fn main() -> anyhow::Result<()> {
     */
 
     //    -----CREATING DEFAULT PRODUCT TYPE -----
-    let default_product_type = create_product_type(
-        Rc::new(RefCell::new(FinalProductType {
-            saleor_id: None,
-            //INFO: MAGIC NUMBER!
-            name: "Základný typ".to_owned(),
-        })),
-        &tax_class_id,
-        &mut client,
-        &jwt,
-    )
-    .await
-    .unwrap()
-    .data
-    .unwrap()
-    .product_type_create
-    .unwrap()
-    .product_type
-    .unwrap()
-    .id;
+    let default_type_key = OldKey::ProductType("Základný typ".to_owned());
+    let default_product_type = match state.id(&default_type_key, Step::Create) {
+        Some(id) => id,
+        None => {
+            let id = create_product_type(
+                Arc::new(RwLock::new(FinalProductType {
+                    saleor_id: None,
+                    //INFO: MAGIC NUMBER!
+                    name: "Základný typ".to_owned(),
+                    attributes: vec![],
+                })),
+                &tax_class_id,
+                &attribute_ids,
+                &saleor,
+            )
+            .await
+            .context("failed creating the default product type")?;
+            state.record(default_type_key, Step::Create, vec![id.clone()])?;
+            id
+        }
+    };
+    //    -----CREATING PRODUCT TYPES FROM THE CATEGORY MAPPING-----
+    // All of them before any product, so the product workers only ever read them
+    for product_type in &product_types {
+        let name = product_type.read().name.clone();
+        let type_key = OldKey::ProductType(name.clone());
+        let id = match state.id(&type_key, Step::Create) {
+            Some(id) => id,
+            None => {
+                println!("creating product type {}", name);
+                let create_product_type_result = create_product_type(
+                    product_type.clone(),
+                    &tax_class_id,
+                    &attribute_ids,
+                    &saleor,
+                )
+                .await;
+                match create_product_type_result {
+                    Err(e) => {
+                        report.record(Outcome::saleor_error("ProductTypeCreate", &name, &name, &e));
+                        continue;
+                    }
+                    Ok(id) => {
+                        state.record(type_key.clone(), Step::Create, vec![id.clone()])?;
+                        report.record(Outcome::ok("ProductTypeCreate", &name, &name));
+                        id
+                    }
+                }
+            }
+        };
+        // Products only get the type once it has its attributes, until then the default one
+        if !state.is_done(&type_key, Step::Attributes) {
+            let attributes = product_type.read().attributes.clone();
+            match assign_attributes(&id, &attributes, &attribute_ids, &saleor).await {
+                Err(e) => {
+                    report.record(Outcome::saleor_error(
+                        "ProductAttributeAssign",
+                        &name,
+                        &name,
+                        &e,
+                    ));
+                    continue;
+                }
+                Ok(()) => {
+                    state.record(type_key, Step::Attributes, vec![])?;
+                    report.record(Outcome::ok("ProductAttributeAssign", &name, &name));
+                }
+            }
+        }
+        product_type.write().saleor_id = Some(id);
+    }
     //    -----UPLOADING CATEGORIES-----
     //2.
     //Categories that don't have a parent_id are guaranteed to be at root, so when the root ones are created
     //I assign it's new Saleor ID to the root ones, and next time someone needs to parent under it with ID it'll be there
-    let mut cat_slugs = vec![];
-    let mut cert_buf: Vec<u8> = vec![];
-    // File::open("root.crt")?.read_to_end(&mut cert_buf)?;
-    // let cert = reqwest::Certificate::from_pem(&cert_buf)?;
-
-    let reqw_client = reqwest::Client::builder()
-        .tls_built_in_root_certs(false)
-        .danger_accept_invalid_certs(true)
-        // IF PROBLEMS WITH LOCALHOST/ SELF SIGNED CERTS!
-        // .add_root_certificate(cert)
-        .build()?;
+    // Slugs an earlier run already gave out or saleor already has stay taken
+    let mut cat_slugs: Vec<String> = categories
+        .iter()
+        .filter_map(|category| {
+            let key = OldKey::Category(category.read().category.read().id);
+            state.slugs(&key).map(|slugs| slugs.new.clone())
+        })
+        .chain(existing_saleor_categories.values().map(|c| c.slug.clone()))
+        .collect();
 
     for category in &categories {
-        let mut category_mut = category.borrow_mut();
-        {
+        let mut category_mut = category.write();
+        let slugs = {
             let category_data = category_mut.category.clone();
-            let mut category_data = category_data.borrow_mut();
+            let mut category_data = category_data.write();
+            let old_slug = category_data.slug.clone();
 
-            //If slug isn't unique, add random stuff at the end and error log it
-            if cat_slugs.contains(&category_data.slug) {
-                use rand::distributions::{Alphanumeric, DistString};
-                let id = Alphanumeric.sample_string(&mut rand::thread_rng(), 4);
-                let slug = category_data.slug.clone();
-                category_data.slug = format!("{}-{}", slug, id);
-                writeln!(
-                    log_file,
-                    "category '{}: {}' failed, code: {}",
-                    &category_data.id, &category_data.name, "Unique"
-                )?;
+            let existing = existing_saleor_categories.get(&category_data.id);
+            let recorded = state.slugs(&OldKey::Category(category_data.id));
+            category_data.slug = match (existing, recorded) {
+                // Updates leave the slug saleor has alone
+                (Some(existing), _) => existing.slug.clone(),
+                // The slug it went up with last time, a new random one would break links to it
+                (None, Some(slugs)) => slugs.new.clone(),
+                //If slug isn't unique, add random stuff at the end and error log it
+                (None, None) if cat_slugs.contains(&old_slug) => {
+                    use rand::distributions::{Alphanumeric, DistString};
+                    let id = Alphanumeric.sample_string(&mut rand::thread_rng(), 4);
+                    let slug = format!("{}-{}", old_slug, id);
+                    cat_slugs.push(slug.clone());
+                    slug
+                }
+                (None, None) => {
+                    cat_slugs.push(old_slug.clone());
+                    old_slug.clone()
+                }
+            };
+            if category_data.slug != old_slug {
+                report.renamed(
+                    "category slug",
+                    category_data.id,
+                    &category_data.name,
+                    &old_slug,
+                    &category_data.slug,
+                );
             }
 
-            cat_slugs.push(category_data.slug.clone());
-
             println!("Creating category {:?}", &category_data.name);
+            Slugs {
+                old: old_slug,
+                new: category_data.slug.clone(),
+            }
+        };
+        let category_key = OldKey::Category(category_mut.category.read().id);
+        if let Some(saleor_id) = state.id(&category_key, Step::Create) {
+            println!("already created, skipping");
+            category_mut.saleor_id = Some(saleor_id);
+            continue;
         }
         //So they can parent eachother as the loop progresses we need to keep the parent_ids alive
         let mut category_parent_id = None;
         let temp_saleor_id;
         if let Some(parent_category) = &mut category_mut.parent_category {
-            if let Some(saleor_id) = &parent_category.borrow().saleor_id {
+            if let Some(saleor_id) = &parent_category.read().saleor_id {
                 temp_saleor_id = saleor_id.clone();
                 category_parent_id = Some(&temp_saleor_id);
             }
         }
         let category_cp = category_mut.category.clone();
-        let category_cp = category_cp.borrow();
-        let category_description =
-            Some(gql_queries::Jsonstring(category_cp.description.to_owned()));
+        let category_cp = category_cp.read();
         let category_old_id = category_cp.id.to_string();
 
+        if let Some(existing) = existing_saleor_categories.get(&category_cp.id) {
+            println!("already in saleor, updating");
+            let saleor_id = &existing.id;
+            let category_update_operation =
+                gql_queries::UpdateCategory::build(gql_queries::UpdateCategoryVariables {
+                    id: saleor_id,
+                    input: gql_queries::CategoryInput {
+                        slug: None,
+                        ..gql_queries::category_input(&category_cp, &category_old_id)
+                    },
+                });
+            match saleor.mutate(category_update_operation).await {
+                Err(e) => report.record(Outcome::saleor_error(
+                    "CategoryUpdate",
+                    category_cp.id,
+                    &category_cp.name,
+                    &e,
+                )),
+                Ok(_) => {
+                    state.record_slugs(
+                        category_key,
+                        Step::Create,
+                        vec![saleor_id.clone()],
+                        slugs,
+                    )?;
+                    report.record(Outcome::ok(
+                        "CategoryUpdate",
+                        category_cp.id,
+                        &category_cp.name,
+                    ))
+                }
+            }
+            // It's in saleor either way, products still go under it and the next --sync retries
+            category_mut.saleor_id = Some(saleor_id.clone());
+            continue;
+        }
+
         let category_create_operation: cynic::Operation<
             gql_queries::CreateCategory,
             gql_queries::CreateCategoryVariables,
         > = gql_queries::CreateCategory::build(gql_queries::CreateCategoryVariables {
-            input: gql_queries::CategoryInput {
-                description: category_description,
-                name: Some(category_cp.name.as_str()),
-                slug: Some(category_cp.slug.as_str()),
-                seo: None,
-                background_image: None,
-                background_image_alt: Some(category_cp.name.as_str()),
-                metadata: Some(vec![MetadataInput {
-                    //INFO: MAGIC NUMBER!
-                    key: "old_id",
-                    value: &category_old_id,
-                }]),
-                private_metadata: None,
-            },
+            input: gql_queries::category_input(&category_cp, &category_old_id),
             parent_id: category_parent_id,
         });
         // dbg!(
         //     &category_create_operation.query,
         //     &category_create_operation.variables,
         // );
-        let create_cat_response: GraphQlResponse<CreateCategory>;
         // TODO: should be &category_mut.image in prod, but I self signed certs are hell with
         // reqwest
-
+        let mut background_image = None;
         if let Some(image) = &category_mut.image {
-            //INFO: MAGIC NUMBER!
-            let file_path_str = format!("/home/djkato/db-migration/media/products/{image}");
-            let file_path = std::path::Path::new(&file_path_str);
-            if file_path.exists() {
-                let mut file_ext = file_path
-                    .extension()
-                    .context("missing image extension")?
-                    .to_str()
-                    .context("missing image extension")?;
-                //INFO: MAGIC NUMBER!
-                if file_ext.to_lowercase() == "jpg" {
-                    file_ext = "jpeg"
-                }
-                let file = std::fs::read(file_path)?;
-                let file_part = Part::bytes(file)
-                    .file_name(image.clone())
-                    .mime_str(format!("image/{file_ext}").as_str())?;
-                let map_part = Part::text(
-                    serde_json::json!(
-                            {
-                                "1": [
-                                    "variables.input.backgroundImage"
-                                ]
-                            }
-                    )
-                    .to_string(),
-                );
-                let gql_part = Part::text(serde_json::to_string(&category_create_operation)?);
-
-                let form = Form::new()
-                    .part("operations", gql_part)
-                    .part("map", map_part)
-                    .part("1", file_part);
-                // And finally, send the form
-
-                let req = reqw_client
-                    .post(GQL_Endpoint)
-                    .bearer_auth(&pure_jwt)
-                    .multipart(form)
-                    .send()
-                    .await?;
-
-                create_cat_response = serde_json::from_str(&req.text().await?)?;
-            } else {
-                create_cat_response = surf::post(GQL_Endpoint)
-                    .header("Authorization", &jwt)
-                    .run_graphql(category_create_operation)
-                    .await
-                    .expect("Failed creating category");
+            if config().media_dir().join(image).exists() {
+                background_image =
+                    Some(FileUpload::read("variables.input.backgroundImage", image)?);
             }
-        } else {
-            create_cat_response = surf::post(GQL_Endpoint)
-                .header("Authorization", &jwt)
-                .run_graphql(category_create_operation)
-                .await
-                .expect("Failed creating category");
         }
-        if let Some(data) = &create_cat_response.data {
-            if let Some(create) = &data.category_create {
-                for error in &create.errors {
-                    dbg!(error);
-                }
+        let create_cat_response = match &background_image {
+            Some(file) => {
+                saleor
+                    .mutate_with_file(category_create_operation, file)
+                    .await
             }
-        }
-        if let Some(errors) = &create_cat_response.errors {
-            dbg!(&errors);
-            dbg!(&create_cat_response);
-        }
-        if let Some(data) = create_cat_response.data {
-            if let Some(create) = data.category_create {
-                for err in create.errors {
-                    writeln!(
-                        log_file,
-                        "category '{}: {}' failed, code: {:?}",
-                        category_mut.category.borrow().id,
-                        category_mut.category.borrow().name,
-                        err.code,
-                    )?;
-                    println!(
-                        "category '{}: {}' failed, code: {:?}",
-                        category_mut.category.borrow().id,
-                        category_mut.category.borrow().name,
-                        err.code,
-                    );
-                }
-                if let Some(cat) = create.category {
-                    category_mut.saleor_id = Some(cat.id)
-                }
+            None => saleor.mutate(category_create_operation).await,
+        };
+        match create_cat_response.map(|d| d.category_create.and_then(|c| c.category)) {
+            Err(e) => report.record(Outcome::saleor_error(
+                "CategoryCreate",
+                category_cp.id,
+                &category_cp.name,
+                &e,
+            )),
+            Ok(Some(cat)) => {
+                state.record_slugs(category_key, Step::Create, vec![cat.id.clone()], slugs)?;
+                category_mut.saleor_id = Some(cat.id);
+                report.record(Outcome::ok(
+                    "CategoryCreate",
+                    category_cp.id,
+                    &category_cp.name,
+                ));
             }
-        } else {
-            category_mut.saleor_id = None;
+            Ok(None) => category_mut.saleor_id = None,
         }
     }
-    //4.
-    //Upload products. Check https://www.notion.so/creating-a-product-5e7397a0234d47038aa8a1689d3e61a8
-    let warehouse_id = cynic::Id::new(Product_Warehouse_ID);
-    let channel_id = cynic::Id::new(Product_Channel_ID);
 
-    for product in &mut products {
-        println!("creating product {}", &product.product.name.clone());
-        let mut category_id = None;
-        let mut cat_temp_id = None;
-        if let Some(curr_cat) = &product.category {
-            cat_temp_id = curr_cat.borrow().saleor_id.clone();
-            category_id = cat_temp_id.as_ref();
-        }
-        if cat_temp_id.is_none() {
+    //    -----CATEGORY TRANSLATIONS-----
+    //3.
+    for category in &categories {
+        let category = category.read();
+        let Some(saleor_id) = &category.saleor_id else {
+            continue;
+        };
+        let category_data = category.category.read();
+        let category_key = OldKey::Category(category_data.id);
+        if category.translations.is_empty() || state.is_done(&category_key, Step::Translations) {
             continue;
         }
-        loop {
-            let create_product_response = create_product(
-                category_id,
-                product,
-                &tax_class_id,
-                &default_product_type,
-                product.product.id,
-                &mut client,
-                &mut jwt,
-            )
-            .await;
-
-            match create_product_response {
-                Err(e) => match e {
-                    SaleorGraphqlError::Other(c) => {
-                        writeln!(
-                            log_file,
-                            "create product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        )?;
-                        println!(
-                            "create product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        );
-                        if c == ProductErrorCode::Unique {
-                            use rand::distributions::{Alphanumeric, DistString};
-                            let id = Alphanumeric.sample_string(&mut rand::thread_rng(), 4);
-                            product.slug = format!("{}-{}", product.slug, id);
-                        } else {
-                            break;
-                        }
-                    }
-                    SaleorGraphqlError::SignatureExpired => (client, jwt) = saleor_login().await?,
-                },
-                Ok(data) => {
-                    if let Some(data) = data.data {
-                        if let Some(product_create) = data.product_create {
-                            if let Some(prd) = product_create.product {
-                                product.saleor_id = Some(prd.id);
-                                break;
-                            }
-                        }
-                    }
-                    panic!()
-                }
-            }
-        }
-
-        loop {
-            let product_channel_listing_update_response =
-                product_channel_listing_update(product, &mut client, &jwt, &channel_id).await;
-            match product_channel_listing_update_response {
-                Err(e) => match e {
-                    SaleorGraphqlError::Other(c) => {
-                        writeln!(
-                            log_file,
-                            "product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        )?;
-                        println!(
-                            "product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        );
-                        break;
-                    }
-                    SaleorGraphqlError::SignatureExpired => (client, jwt) = saleor_login().await?,
-                },
-                Ok(_) => break,
-            }
-        }
-        let mut variant_id = None;
-        loop {
-            let variant_create_response =
-                variant_create(product, &mut client, &jwt, &warehouse_id).await;
-            match variant_create_response {
-                Ok(v) => {
-                    variant_id = Some(
-                        v.data
-                            .context("no data variant response")?
-                            .product_variant_create
-                            .context("no variant create response")?
-                            .product_variant
-                            .context("no product_variant create response")?
-                            .id,
-                    );
-                    break;
-                }
-                Err(e) => match e {
-                    SaleorGraphqlError::Other(c) => {
-                        writeln!(
-                            log_file,
-                            "product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        )?;
-                        println!(
-                            "product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        );
-                        break;
-                    }
-                    SaleorGraphqlError::SignatureExpired => (client, jwt) = saleor_login().await?,
-                },
-            }
-        }
-
-        if let Some(variant_id) = &variant_id {
-            loop {
-                let variant_listing_update_response =
-                    variant_listing_update(product, &variant_id, &channel_id, &mut client, &jwt)
-                        .await;
-                match variant_listing_update_response {
-                    Ok(_) => break,
-                    Err(e) => match e {
-                        SaleorGraphqlError::Other(c) => {
-                            writeln!(
-                                log_file,
-                                "product '{}: {}' failed, code: {:?}",
-                                product.product.id, product.product.name, c,
-                            )?;
-                            println!(
-                                "product '{}: {}' failed, code: {:?}",
-                                product.product.id, product.product.name, c,
-                            );
-                            break;
-                        }
-                        SaleorGraphqlError::SignatureExpired => {
-                            (client, jwt) = saleor_login().await?
-                        }
-                    },
-                }
-            }
-        }
-
-        //5.
-        //Upload media for products. https://docs.saleor.io/docs/3.x/api-reference/products/inputs/product-media-create-input#
-        let mut media_ids: Vec<cynic::Id> = vec![];
-        loop {
-            let media_create_responses = product_media_create(product, &mut client, &mut jwt).await;
-            match media_create_responses {
-                Ok(r) => {
-                    media_ids.append(
-                        &mut r
-                            .into_iter()
-                            .filter_map(|d| {
-                                d.data.and_then(|o| {
-                                    o.product_media_create
-                                        .and_then(|ot| ot.media.map(|oto| oto.id))
-                                })
-                            })
-                            .collect::<Vec<cynic::Id>>(),
-                    );
-                    break;
-                }
-                Err(e) => match e {
-                    SaleorGraphqlError::Other(c) => {
-                        writeln!(
-                            log_file,
-                            "product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        )?;
-                        println!(
-                            "product '{}: {}' failed, code: {:?}",
-                            product.product.id, product.product.name, c,
-                        );
-                        break;
-                    }
-                    SaleorGraphqlError::SignatureExpired => (client, jwt) = saleor_login().await?,
-                },
+        let mut all_translated = true;
+        for translation in &category.translations {
+            if let Err(e) = category_translate(saleor_id, translation, &saleor).await {
+                report.record(Outcome::saleor_error(
+                    "CategoryTranslate",
+                    category_data.id,
+                    &category_data.name,
+                    &e,
+                ));
+                all_translated = false;
             }
         }
-
-        //6.
-        // Assing media to the variant we created
-        if let Some(variant_id) = variant_id {
-            println!("variant_id:{:?}, media_ids: {:?}", &variant_id, &media_ids);
-            for media_id in media_ids {
-                loop {
-                    let variant_media_assign_response =
-                        variant_media_assign(&variant_id, &media_id, &mut client, &jwt).await;
-                    match variant_media_assign_response {
-                        Ok(_) => break,
-                        Err(e) => match e {
-                            SaleorGraphqlError::Other(c) => {
-                                writeln!(
-                                    log_file,
-                                    "product '{}: {}' failed, code: {:?}",
-                                    product.product.id, product.product.name, c,
-                                )?;
-                                println!(
-                                    "product '{}: {}' failed, code: {:?}",
-                                    product.product.id, product.product.name, c,
-                                );
-                                break;
-                            }
-                            SaleorGraphqlError::SignatureExpired => {
-                                (client, jwt) = saleor_login().await?
-                            }
-                        },
-                    }
-                }
-            }
+        if all_translated {
+            state.record(category_key, Step::Translations, vec![])?;
+            report.record(Outcome::ok(
+                "CategoryTranslate",
+                category_data.id,
+                &category_data.name,
+            ));
         }
     }
-    anyhow::Ok(())
-}
+    //4.
+    //Upload products. Check https://www.notion.so/creating-a-product-5e7397a0234d47038aa8a1689d3e61a8
+    let pipeline = ProductPipeline {
+        state: Mutex::new(state),
+        report,
+        saleor: &saleor,
+        tax_class_id,
+        default_product_type,
+        attribute_ids,
+        warehouse_id: shop_ids.warehouse_id,
+        channel_ids: shop_ids.channel_ids,
+        existing_products: &existing_saleor_products,
+    };
+    println!(
+        "Uploading {} products, {} at a time...",
+        products.len(),
+        config().concurrency
+    );
+    pipeline.run(&mut products).await;
 
-use async_native_tls::{Certificate, TlsConnector};
-use std::sync::Arc;
-use surf::Client;
+    //5.
+    // Old accounts, once the catalogue is up
+    if config().customers.is_some() {
+        println!("Querying customers from Old db...");
+        let customers = query_customers().await?;
+        let existing_saleor_customers = match sync {
+            true => existing_customers(&saleor).await?,
+            false => HashMap::new(),
+        };
+        customers::migrate_customers(
+            &customers,
+            &existing_saleor_customers,
+            &pipeline.state,
+            report,
+            &saleor,
+        )
+        .await;
+    }
 
-async fn saleor_login() -> anyhow::Result<(Client, String)> {
-    let tls_connector = Some(Arc::new(
-        async_native_tls::TlsConnector::new()
-            .danger_accept_invalid_certs(true)
-            .danger_accept_invalid_hostnames(true),
-    ));
+    //6.
+    // Past orders as history, their lines need the variants and the customers
+    if let Some(settings) = &config().orders {
+        println!("Querying orders from Old db...");
+        let orders = query_orders().await?;
+        orders::migrate_orders(
+            &orders,
+            settings,
+            &products,
+            &pipeline.state,
+            report,
+            &saleor,
+            &pipeline.warehouse_id,
+        )
+        .await;
+    }
 
-    let config = surf::Config::new().set_tls_config(tls_connector);
-    let client: Client = config.try_into()?;
+    //7.
+    // Old discounts as sales, on the products and categories that made it
+    if config().discounts.is_some() {
+        let discounts = discounts::group(&categories, &products);
+        let existing_sales = match sync {
+            true => existing_sales(&saleor).await?,
+            false => HashMap::new(),
+        };
+        discounts::migrate_discounts(
+            &discounts,
+            &existing_sales,
+            &pipeline.state,
+            report,
+            &saleor,
+            &pipeline.channel_ids,
+        )
+        .await;
+    }
 
-    println!("Logging into saleor...");
-    let login_operation = gql_queries::CreateToken::build(CreateTokenVariables {
-        email: dotenv!("EMAIL"),
-        password: dotenv!("PASS"),
-    });
-    dbg!(&GQL_Endpoint);
-    let login_response = client
-        .post(GQL_Endpoint)
-        .run_graphql(login_operation)
-        .await
-        .unwrap();
-    let jwt = format!(
-        "Bearer {}",
-        login_response
-            .data
-            .context("")?
-            .token_create
-            .context("")?
-            .token
-            .context("")?
-    );
-    println!("Success!");
-    Ok((client, jwt))
+    if let Some(old_urls) = old_urls {
+        old_urls.write(&categories, &products)?;
+    }
+    anyhow::Ok(())
 }
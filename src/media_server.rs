@@ -0,0 +1,254 @@
+use crate::config::config;
+use anyhow::Context;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Serves `media_dir()` over plain http for the duration of the run, saleor downloads every
+/// product picture from it through `mediaUrl`. Stops when dropped
+pub struct MediaServer {
+    task: JoinHandle<()>,
+}
+
+impl MediaServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        let bind = config().media_server.bind;
+        let found_base_url = base_url()?;
+        let base_url = BASE_URL.get_or_init(|| found_base_url);
+        let listener = TcpListener::bind(bind)
+            .await
+            .with_context(|| format!("Failed to start media server on {}", bind))?;
+        println!("Serving {} on {}", config().media_dir().display(), base_url);
+        let root = config().media_dir().to_path_buf();
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        println!("media server failed to accept a connection: {}", e);
+                        continue;
+                    }
+                };
+                let root = root.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &root).await {
+                        println!("media server failed to answer: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(MediaServer { task })
+    }
+}
+
+impl Drop for MediaServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        println!("Stopped media server");
+    }
+}
+
+/// Set by `MediaServer::start`
+static BASE_URL: OnceLock<String> = OnceLock::new();
+
+/// Url saleor can download `file_name` (relative to `media_dir()`) from. Without a started server,
+/// as in a dry run, the local ip isn't looked up and a stand-in takes its place
+pub fn url(file_name: &str) -> String {
+    let base_url = match BASE_URL.get() {
+        Some(base_url) => base_url.clone(),
+        None => match &config().media_server.public_url {
+            Some(public_url) => public_url.trim_end_matches('/').to_owned(),
+            None => format!("http://local-ip:{}", config().media_server.bind.port()),
+        },
+    };
+    format!("{}/{}", base_url, encode(file_name))
+}
+
+fn base_url() -> anyhow::Result<String> {
+    match &config().media_server.public_url {
+        Some(public_url) => Ok(public_url.trim_end_matches('/').to_owned()),
+        None => {
+            let ip_address = local_ip_address::local_ip()
+                .context("Failed finding local IP. Are you offline?")?;
+            Ok(format!(
+                "http://{}:{}",
+                ip_address,
+                config().media_server.bind.port()
+            ))
+        }
+    }
+}
+
+/// Answers a single GET or HEAD, then closes the connection
+async fn serve(stream: TcpStream, root: &Path) -> anyhow::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    // Headers don't matter, they just have to be read before answering
+    loop {
+        let mut header = String::new();
+        if stream.read_line(&mut header).await? <= 2 {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", 0, b"").await;
+    }
+    let file = match resolve(root, target) {
+        Some(path) => tokio::fs::read(&path).await.ok().map(|bytes| (path, bytes)),
+        None => None,
+    };
+    let Some((path, bytes)) = file else {
+        println!("media server: {} not found", target);
+        return respond(&mut stream, "404 Not Found", "text/plain", 0, b"").await;
+    };
+    // HEAD still tells the length of what GET would send
+    let body = match method {
+        "HEAD" => &[][..],
+        _ => &bytes[..],
+    };
+    respond(&mut stream, "200 OK", mime(&path), bytes.len(), body).await
+}
+
+async fn respond(
+    stream: &mut (impl AsyncWrite + Unpin),
+    status: &str,
+    content_type: &str,
+    content_length: usize,
+    body: &[u8],
+) -> anyhow::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, content_type, content_length
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// File under `root` the request path points to, nothing outside of it
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next()?;
+    let path = decode(path)?;
+    let mut file = root.to_path_buf();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => file.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    file.is_file().then_some(file)
+}
+
+/// Percent encodes everything but unreserved characters and slashes, old file names have spaces
+/// and diacritics in them
+fn encode(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)?;
+                // from_str_radix would take a sign too
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+pub fn mime(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_round_trip() {
+        let name = "obrázky/Tričko modré (2)+100%.jpg";
+        let encoded = encode(name);
+        assert_eq!(
+            encoded,
+            "obr%C3%A1zky/Tri%C4%8Dko%20modr%C3%A9%20%282%29%2B100%25.jpg"
+        );
+        assert_eq!(decode(&encoded).as_deref(), Some(name));
+    }
+
+    #[test]
+    fn decode_refuses_broken_escapes() {
+        assert_eq!(decode("a%2"), None);
+        assert_eq!(decode("a%zz"), None);
+        assert_eq!(decode("a%+1"), None);
+        // not utf-8
+        assert_eq!(decode("a%FF"), None);
+    }
+
+    #[test]
+    fn resolve_stays_inside_the_root() {
+        let dir = std::env::temp_dir().join(format!("media-server-test-{}", std::process::id()));
+        let root = dir.join("media");
+        std::fs::create_dir_all(root.join("a b")).unwrap();
+        std::fs::write(root.join("a b").join("č.jpg"), b"jpg").unwrap();
+        std::fs::write(dir.join("secret.txt"), b"secret").unwrap();
+
+        let picture = Some(root.join("a b").join("č.jpg"));
+        assert_eq!(resolve(&root, "/a%20b/%C4%8D.jpg"), picture);
+        assert_eq!(resolve(&root, "/a%20b/./%C4%8D.jpg?v=2#top"), picture);
+        assert_eq!(resolve(&root, "//a%20b/%C4%8D.jpg"), picture);
+        assert_eq!(resolve(&root, "/a%20b"), None);
+        assert_eq!(resolve(&root, "/missing.jpg"), None);
+        for traversal in [
+            "/../secret.txt",
+            "/%2E%2E/secret.txt",
+            "/a%20b/../../secret.txt",
+            "/..%2Fsecret.txt",
+            "/a%20b/%2e%2e%2f%2e%2e%2fsecret.txt",
+        ] {
+            assert_eq!(resolve(&root, traversal), None, "{}", traversal);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
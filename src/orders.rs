@@ -0,0 +1,253 @@
+use crate::config::{config, MissingLines, OrdersConfig};
+use crate::customers::validate_address;
+use crate::get_sqls::{FinalOrder, FinalProduct, OrderLine, UserAddress};
+use crate::gql_queries::{
+    order_bulk_create, order_bulk_create_input, order_line_input, CountryCode, DateTime,
+    OrderBulkCreateInput, ORDER_BULK_LIMIT,
+};
+use crate::report::{Outcome, Report};
+use crate::saleor::{MutationError, Saleor, SaleorError};
+use crate::state::{MigrationState, OldKey, Step};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// Saleor variant id by old product id
+pub type Variants = HashMap<u32, cynic::Id>;
+
+/// An old order with everything its input needs looked up
+pub struct PreparedOrder<'a> {
+    pub order: &'a FinalOrder,
+    pub old_id: String,
+    pub created_at: DateTime,
+    /// Saleor customer of the old account, the order goes under its email otherwise
+    pub customer_id: Option<cynic::Id>,
+    pub billing_country: CountryCode,
+    pub shipping: Option<(&'a UserAddress, CountryCode)>,
+    pub lines: Vec<(&'a OrderLine, &'a cynic::Id)>,
+    /// Lines left out with `MissingLines::Metadata`, as JSON
+    pub missing_lines: Option<String>,
+}
+
+/// Imports the old orders as history with orderBulkCreate, lines point at the variants their
+/// products became. Stock stays as it is and nobody gets an email
+pub async fn migrate_orders(
+    orders: &[FinalOrder],
+    settings: &OrdersConfig,
+    products: &[FinalProduct],
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+    warehouse_id: &cynic::Id,
+) {
+    let variants = variants(products, &state.lock());
+    let prepared: Vec<PreparedOrder> = orders
+        .iter()
+        .filter(|order| {
+            !state
+                .lock()
+                .is_done(&OldKey::Order(order.order.id), Step::Create)
+        })
+        .filter_map(|order| {
+            let mut prepared = prepare(order, &variants, settings, report)?;
+            prepared.customer_id = order
+                .order
+                .user_id
+                .and_then(|user_id| state.lock().id(&OldKey::Customer(user_id), Step::Create));
+            Some(prepared)
+        })
+        .collect();
+    let bulk_size = config()
+        .bulk_size
+        .unwrap_or(ORDER_BULK_LIMIT)
+        .clamp(1, ORDER_BULK_LIMIT);
+    println!(
+        "Importing {} orders, {} at a time...",
+        prepared.len(),
+        bulk_size
+    );
+    for batch in prepared.chunks(bulk_size) {
+        if let Err(e) = create_batch(batch, settings, state, report, saleor, warehouse_id).await {
+            for order in batch {
+                report.record(Outcome::error(
+                    "OrderBulkCreate",
+                    order.order.order.id,
+                    &order.order.order.number,
+                    format!("{:?}", e),
+                ));
+            }
+        }
+    }
+}
+
+/// Every old product whose variant is in saleor now, family members included. A product that
+/// got created without its variant doesn't count
+pub fn variants(products: &[FinalProduct], state: &MigrationState) -> Variants {
+    products
+        .iter()
+        .flat_map(|product| std::iter::once(product).chain(&product.siblings))
+        .filter_map(|variant| {
+            let id = state.id(&OldKey::Product(variant.product.id), Step::Variant)?;
+            Some((variant.product.id, id))
+        })
+        .collect()
+}
+
+/// `None` if the order can't go in at all, the reason is in the report then. Lines of products
+/// that aren't in saleor are reported on their own, `settings.missing_lines` decides whether the
+/// order goes in without them
+pub fn prepare<'a>(
+    order: &'a FinalOrder,
+    variants: &'a Variants,
+    settings: &OrdersConfig,
+    report: &Report,
+) -> Option<PreparedOrder<'a>> {
+    let old = &order.order;
+    let skip = |reason: &str| {
+        println!("order '{}: {}' left out, {}", old.id, old.number, reason);
+        report.skipped(old.id, &old.number, reason);
+    };
+    let Some(created_at) = old.created_at else {
+        skip("no order date");
+        return None;
+    };
+    let billing_country = match validate_address(&order.billing) {
+        Ok(country) => country,
+        Err(reason) => {
+            skip(&format!("billing address: {}", reason));
+            return None;
+        }
+    };
+    let shipping = match &order.shipping {
+        Some(address) => match validate_address(address) {
+            Ok(country) => Some((address, country)),
+            Err(reason) => {
+                // Saleor takes orders without one, the billing address is still there
+                println!(
+                    "delivery address of order '{}: {}' left out: {}",
+                    old.id, old.number, reason
+                );
+                None
+            }
+        },
+        None => None,
+    };
+    let mut lines = vec![];
+    let mut missing = vec![];
+    for line in &order.lines {
+        match line.product_id.and_then(|id| variants.get(&id)) {
+            Some(variant_id) => lines.push((line, variant_id)),
+            None => {
+                report.missing_line(old.id, &old.number, line.product_id, &line.name);
+                missing.push(line);
+            }
+        }
+    }
+    let missing_lines = match (missing.is_empty(), settings.missing_lines) {
+        (true, _) => None,
+        (false, MissingLines::Skip) => {
+            skip("lines of products that aren't in saleor, the totals wouldn't match");
+            return None;
+        }
+        (false, MissingLines::Metadata) => Some(missing_lines_metadata(&missing)),
+    };
+    if lines.is_empty() {
+        skip("no line with a product that's in saleor");
+        return None;
+    }
+    Some(PreparedOrder {
+        order,
+        old_id: old.id.to_string(),
+        created_at: DateTime(created_at.to_rfc3339()),
+        customer_id: None,
+        billing_country,
+        shipping,
+        lines,
+        missing_lines,
+    })
+}
+
+/// What the old shop sold on the lines saleor has no product for, so the order still adds up
+fn missing_lines_metadata(lines: &[&OrderLine]) -> String {
+    let lines: Vec<serde_json::Value> = lines
+        .iter()
+        .map(|line| {
+            serde_json::json!({
+                "product_id": line.product_id,
+                "name": line.name,
+                "code": line.code,
+                "quantity": line.quantity,
+                "price": line.price.to_string(),
+                "price_with_vat": line.price_with_vat.to_string(),
+            })
+        })
+        .collect();
+    serde_json::Value::from(lines).to_string()
+}
+
+/// orderBulkCreate rows of a batch, every line taken from `warehouse_id`
+pub fn inputs<'a>(
+    batch: &'a [PreparedOrder<'a>],
+    settings: &'a OrdersConfig,
+    warehouse_id: &'a cynic::Id,
+) -> Vec<OrderBulkCreateInput<'a>> {
+    batch
+        .iter()
+        .map(|prepared| {
+            let lines = prepared
+                .lines
+                .iter()
+                .map(|(line, variant_id)| {
+                    order_line_input(line, variant_id, &prepared.created_at, warehouse_id)
+                })
+                .collect();
+            order_bulk_create_input(prepared, lines, settings)
+        })
+        .collect()
+}
+
+async fn create_batch(
+    batch: &[PreparedOrder<'_>],
+    settings: &OrdersConfig,
+    state: &Mutex<MigrationState>,
+    report: &Report,
+    saleor: &Saleor,
+    warehouse_id: &cynic::Id,
+) -> anyhow::Result<()> {
+    let results = match order_bulk_create(inputs(batch, settings, warehouse_id), saleor).await {
+        Ok(results) => results,
+        Err(e) => {
+            for prepared in batch {
+                report.record(Outcome::saleor_error(
+                    "OrderBulkCreate",
+                    prepared.order.order.id,
+                    &prepared.order.order.number,
+                    &e,
+                ));
+            }
+            return Ok(());
+        }
+    };
+    for (prepared, result) in batch.iter().zip(results) {
+        let old = &prepared.order.order;
+        let errors = result.errors.unwrap_or_default();
+        match result.order {
+            Some(order) if errors.is_empty() => {
+                state
+                    .lock()
+                    .record(OldKey::Order(old.id), Step::Create, vec![order.id])?;
+                report.record(Outcome::ok("OrderBulkCreate", old.id, &old.number));
+            }
+            _ => {
+                let errors: Vec<MutationError<_>> =
+                    errors.iter().map(|e| e.to_mutation_error()).collect();
+                report.record(Outcome::saleor_error(
+                    "OrderBulkCreate",
+                    old.id,
+                    &old.number,
+                    &SaleorError::Mutation(errors),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,709 @@
+use crate::config::{config, MediaUpload};
+use crate::get_sqls::FinalProduct;
+use crate::gql_queries::{
+    create_product, file_upload, product_bulk_create, product_bulk_create_input,
+    product_channel_listing_update, product_media_create, product_translate, product_type_of,
+    update_metadata, update_product, variant_create, variant_listing_update, variant_media_assign,
+    AttributeIds, CreatedProduct, ExistingProduct, MediaFailure, MetadataInput,
+    ProductBulkCreateErrorCode, ProductErrorCode,
+};
+use crate::report::{Outcome, Report};
+use crate::saleor::{FileUpload, Saleor, SaleorError};
+use crate::state::{MigrationState, OldKey, Slugs, Step};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// Everything the product workers share. Categories and product types are already in saleor by
+/// the time this runs, so products don't depend on each other anymore
+pub struct ProductPipeline<'a> {
+    pub state: Mutex<MigrationState>,
+    pub report: &'a Report,
+    pub saleor: &'a Saleor,
+    pub tax_class_id: cynic::Id,
+    pub default_product_type: cynic::Id,
+    pub attribute_ids: AttributeIds,
+    pub warehouse_id: cynic::Id,
+    pub channel_ids: HashMap<String, cynic::Id>,
+    pub existing_products: &'a HashMap<u32, ExistingProduct>,
+}
+
+impl ProductPipeline<'_> {
+    /// Uploads up to `concurrency` products at the same time
+    pub async fn run(&self, products: &mut [FinalProduct]) {
+        if let Some(bulk_size) = config().bulk_size {
+            self.run_bulk(products, bulk_size).await;
+        }
+        // After a bulk run this only assigns media to the variants and retries the refused rows
+        futures::stream::iter(products.iter_mut())
+            .for_each_concurrent(config().concurrency, |product| async move {
+                if let Err(e) = self.migrate_product(product).await {
+                    self.report.record(Outcome::error(
+                        "Product",
+                        product.product.id,
+                        &product.product.name,
+                        format!("{:?}", e),
+                    ));
+                }
+            })
+            .await;
+    }
+
+    /// Creates the products `bulk_size` at a time with productBulkCreate, together with their
+    /// variants, listings, stock and media. Whatever saleor refuses stays undone in the state and
+    /// goes through the single product path afterwards
+    async fn run_bulk(&self, products: &mut [FinalProduct], bulk_size: usize) {
+        let mut pending: Vec<&mut FinalProduct> = products
+            .iter_mut()
+            .filter(|product| self.bulk_eligible(product))
+            .collect();
+        println!(
+            "Bulk creating {} products, {} per request...",
+            pending.len(),
+            bulk_size
+        );
+        futures::stream::iter(pending.chunks_mut(bulk_size.max(1)))
+            .for_each_concurrent(config().concurrency, |batch| async move {
+                if let Err(e) = self.migrate_batch(batch).await {
+                    for product in batch.iter() {
+                        self.report.record(Outcome::saleor_error(
+                            "ProductBulkCreate",
+                            product.product.id,
+                            &product.product.name,
+                            &e,
+                        ));
+                    }
+                }
+            })
+            .await;
+    }
+
+    /// Only brand new products, updating existing ones and resuming half done ones is left to the
+    /// single product path
+    fn bulk_eligible(&self, product: &FinalProduct) -> bool {
+        let in_saleor = product
+            .category
+            .as_ref()
+            .is_some_and(|c| c.read().saleor_id.is_some());
+        in_saleor
+            && !self.existing_products.contains_key(&product.product.id)
+            && !self
+                .state
+                .lock()
+                .is_done(&OldKey::Product(product.product.id), Step::Create)
+    }
+
+    async fn migrate_batch(
+        &self,
+        batch: &mut [&mut FinalProduct],
+    ) -> Result<(), SaleorError<ProductBulkCreateErrorCode>> {
+        // What the inputs borrow from
+        let prepared: Vec<(String, Option<cynic::Id>, cynic::Id, Vec<String>)> = batch
+            .iter()
+            .map(|product| {
+                let category_id = product
+                    .category
+                    .as_ref()
+                    .and_then(|c| c.read().saleor_id.clone());
+                let (product_type_id, type_attributes) =
+                    product_type_of(product, &self.default_product_type);
+                (
+                    product.product.id.to_string(),
+                    category_id,
+                    product_type_id,
+                    type_attributes,
+                )
+            })
+            .collect();
+        let inputs = batch
+            .iter()
+            .zip(&prepared)
+            .map(
+                |(product, (old_id, category_id, product_type_id, type_attributes))| {
+                    product_bulk_create_input(
+                        category_id.as_ref(),
+                        product,
+                        old_id,
+                        &self.tax_class_id,
+                        product_type_id,
+                        type_attributes,
+                        &self.attribute_ids,
+                        &self.warehouse_id,
+                        &self.channel_ids,
+                    )
+                },
+            )
+            .collect();
+        let results = product_bulk_create(inputs, self.saleor).await?;
+        for (product, result) in batch.iter_mut().zip(results) {
+            match result.product {
+                Some(created) => {
+                    if let Err(e) = self.record_bulk(product, created) {
+                        self.report.record(Outcome::error(
+                            "ProductBulkCreate",
+                            product.product.id,
+                            &product.product.name,
+                            format!("{:?}", e),
+                        ));
+                    }
+                }
+                // Left undone, the single product path gets to try it
+                None => {
+                    let errors = result
+                        .errors
+                        .iter()
+                        .flatten()
+                        .map(|e| e.to_mutation_error())
+                        .collect();
+                    self.report.record(Outcome::saleor_error(
+                        "ProductBulkCreate",
+                        product.product.id,
+                        &product.product.name,
+                        &SaleorError::Mutation(errors),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks every step the bulk create did as done, so the single product path skips them
+    fn record_bulk(
+        &self,
+        product: &mut FinalProduct,
+        created: CreatedProduct,
+    ) -> anyhow::Result<()> {
+        let key = OldKey::Product(product.product.id);
+        let mut state = self.state.lock();
+        state.record(key.clone(), Step::Create, vec![created.id.clone()])?;
+        state.record(key, Step::ChannelListing, vec![])?;
+        product.saleor_id = Some(created.id);
+        let variants = created.variants.unwrap_or_default();
+        // Media come back in the order they were sent, a picture several family members share was
+        // only sent for the first one
+        let mut media = created.media.unwrap_or_default().into_iter();
+        let mut family_media: HashMap<&str, cynic::Id> = HashMap::new();
+        let head_id = product.product.id;
+        for variant in std::iter::once(&*product).chain(product.siblings.iter()) {
+            let key = OldKey::Product(variant.product.id);
+            if let Some(created_variant) = variants
+                .iter()
+                .find(|v| v.sku.as_deref() == Some(variant.SKU.as_str()))
+            {
+                state.record(key.clone(), Step::Variant, vec![created_variant.id.clone()])?;
+                state.record(key.clone(), Step::VariantListing, vec![])?;
+            }
+            // Uploaded pictures weren't part of the request, the single product path sends them
+            if config().media_upload == MediaUpload::Url {
+                let mut media_ids = vec![];
+                for image in &variant.images {
+                    let id = match family_media.get(image.as_str()) {
+                        Some(id) => id.clone(),
+                        None => match media.next() {
+                            Some(created_media) => {
+                                let picture = OldKey::Media(head_id, image.clone());
+                                state.record(
+                                    picture,
+                                    Step::Create,
+                                    vec![created_media.id.clone()],
+                                )?;
+                                created_media.id
+                            }
+                            None => break,
+                        },
+                    };
+                    family_media.insert(image, id.clone());
+                    media_ids.push(id);
+                }
+                // Pictures saleor didn't take are left to the single product path, which only
+                // uploads the ones missing from the state
+                if media_ids.len() == variant.images.len() {
+                    state.record(key, Step::Media, media_ids)?;
+                }
+            }
+        }
+        self.report.record(Outcome::ok(
+            "ProductBulkCreate",
+            product.product.id,
+            &product.product.name,
+        ));
+        Ok(())
+    }
+
+    /// Every step of a single product, each one skipped if the state already has it
+    async fn migrate_product(&self, product: &mut FinalProduct) -> anyhow::Result<()> {
+        println!("creating product {}", &product.product.name.clone());
+        let mut category_id = None;
+        let mut cat_temp_id = None;
+        if let Some(curr_cat) = &product.category {
+            cat_temp_id = curr_cat.read().saleor_id.clone();
+            category_id = cat_temp_id.as_ref();
+        }
+        if cat_temp_id.is_none() {
+            self.report.skipped(
+                product.product.id,
+                &product.product.name,
+                "category isn't in saleor",
+            );
+            return Ok(());
+        }
+        let key = OldKey::Product(product.product.id);
+        // The slug it went up with last time, the redirects lead there
+        if let Some(slugs) = self.state.lock().slugs(&key) {
+            product.slug = slugs.new.clone();
+        }
+        let existing = self.existing_products.get(&product.product.id);
+        let created = self.state.lock().id(&key, Step::Create);
+        if let Some(saleor_id) = created {
+            product.saleor_id = Some(saleor_id);
+        } else if let Some(existing) = existing {
+            println!("already in saleor, updating");
+            // Updates leave the slug saleor has alone
+            product.slug = existing.slug.clone();
+            let update_product_response = update_product(
+                &existing.id,
+                category_id,
+                product,
+                &self.tax_class_id,
+                self.saleor,
+            )
+            .await;
+            match update_product_response {
+                Err(e) => self.report.record(Outcome::saleor_error(
+                    "ProductUpdate",
+                    product.product.id,
+                    &product.product.name,
+                    &e,
+                )),
+                Ok(()) => {
+                    let slugs = Slugs {
+                        old: product.old_slug.clone(),
+                        new: product.slug.clone(),
+                    };
+                    self.state.lock().record_slugs(
+                        key.clone(),
+                        Step::Create,
+                        vec![existing.id.clone()],
+                        slugs,
+                    )?;
+                    self.report.record(Outcome::ok(
+                        "ProductUpdate",
+                        product.product.id,
+                        &product.product.name,
+                    ));
+                }
+            }
+            product.saleor_id = Some(existing.id.clone());
+        } else {
+            let mut retries = 0;
+            loop {
+                let create_product_response = create_product(
+                    category_id,
+                    product,
+                    &self.tax_class_id,
+                    &self.default_product_type,
+                    &self.attribute_ids,
+                    product.product.id,
+                    self.saleor,
+                )
+                .await;
+
+                match create_product_response {
+                    Err(e) => {
+                        self.report.record(
+                            Outcome::saleor_error(
+                                "ProductCreate",
+                                product.product.id,
+                                &product.product.name,
+                                &e,
+                            )
+                            .retries(retries),
+                        );
+                        if !e.has_code(ProductErrorCode::Unique) {
+                            break;
+                        }
+                        use rand::distributions::{Alphanumeric, DistString};
+                        let id = Alphanumeric.sample_string(&mut rand::thread_rng(), 4);
+                        let slug = format!("{}-{}", product.slug, id);
+                        self.report.renamed(
+                            "product slug",
+                            product.product.id,
+                            &product.product.name,
+                            &product.slug,
+                            &slug,
+                        );
+                        product.slug = slug;
+                        retries += 1;
+                    }
+                    Ok(id) => {
+                        let slugs = Slugs {
+                            old: product.old_slug.clone(),
+                            new: product.slug.clone(),
+                        };
+                        self.state.lock().record_slugs(
+                            key.clone(),
+                            Step::Create,
+                            vec![id.clone()],
+                            slugs,
+                        )?;
+                        product.saleor_id = Some(id);
+                        self.report.record(
+                            Outcome::ok("ProductCreate", product.product.id, &product.product.name)
+                                .retries(retries),
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+        // Creating it failed and got reported, the remaining steps have nothing to attach to
+        let Some(product_saleor_id) = product.saleor_id.clone() else {
+            return Ok(());
+        };
+        let listed = self.state.lock().is_done(&key, Step::ChannelListing);
+        if !listed {
+            let product_channel_listing_update_response = product_channel_listing_update(
+                &product_saleor_id,
+                product,
+                &self.channel_ids,
+                self.saleor,
+            )
+            .await;
+            match product_channel_listing_update_response {
+                Err(e) => self.report.record(Outcome::saleor_error(
+                    "ChannelListing",
+                    product.product.id,
+                    &product.product.name,
+                    &e,
+                )),
+                Ok(()) => {
+                    self.state
+                        .lock()
+                        .record(key.clone(), Step::ChannelListing, vec![])?;
+                    self.report.record(Outcome::ok(
+                        "ChannelListing",
+                        product.product.id,
+                        &product.product.name,
+                    ));
+                }
+            }
+        }
+        // Every family member becomes a variant of the product, each keeps its own old id in the
+        // state so the steps below resume per variant
+        let mut siblings = std::mem::take(&mut product.siblings);
+        for sibling in siblings.iter_mut() {
+            sibling.saleor_id = product.saleor_id.clone();
+        }
+        let single_variant = siblings.is_empty();
+        // Uploaded pictures are kept under the family head, so members sharing one find it even
+        // after a resume
+        let head_id = product.product.id;
+        for product in std::iter::once(&mut *product).chain(siblings.iter_mut()) {
+            let key = OldKey::Product(product.product.id);
+            let recorded_variant_id = self.state.lock().id(&key, Step::Variant);
+            let mut variant_id = match recorded_variant_id {
+                Some(id) => Some(id),
+                None => {
+                    let existing_variant_id = existing
+                        .and_then(|e| e.variants.as_ref())
+                        .and_then(|variants| {
+                            variants
+                                .iter()
+                                .find(|v| v.sku.as_deref() == Some(product.SKU.as_str()))
+                                .or_else(|| variants.first().filter(|_| single_variant))
+                        })
+                        .map(|variant| variant.id.clone());
+                    // Sales and orders find variants through the state, synced ones included
+                    if let Some(id) = &existing_variant_id {
+                        self.state
+                            .lock()
+                            .record(key.clone(), Step::Variant, vec![id.clone()])?;
+                    }
+                    existing_variant_id
+                }
+            };
+            if variant_id.is_none() {
+                let variant_create_response = variant_create(
+                    &product_saleor_id,
+                    product,
+                    &self.warehouse_id,
+                    &self.attribute_ids,
+                    self.saleor,
+                )
+                .await;
+                match variant_create_response {
+                    Ok(id) => {
+                        self.state
+                            .lock()
+                            .record(key.clone(), Step::Variant, vec![id.clone()])?;
+                        self.report.record(Outcome::ok(
+                            "VariantCreate",
+                            product.product.id,
+                            &product.product.name,
+                        ));
+                        variant_id = Some(id);
+                    }
+                    Err(e) => self.report.record(Outcome::saleor_error(
+                        "VariantCreate",
+                        product.product.id,
+                        &product.product.name,
+                        &e,
+                    )),
+                }
+            }
+
+            if let Some(variant_id) = &variant_id {
+                let listed = self.state.lock().is_done(&key, Step::VariantListing);
+                if !listed {
+                    let variant_listing_update_response =
+                        variant_listing_update(product, variant_id, &self.channel_ids, self.saleor)
+                            .await;
+                    match variant_listing_update_response {
+                        Ok(()) => {
+                            self.state
+                                .lock()
+                                .record(key.clone(), Step::VariantListing, vec![])?;
+                            self.report.record(Outcome::ok(
+                                "VariantListing",
+                                product.product.id,
+                                &product.product.name,
+                            ));
+                        }
+                        Err(e) => self.report.record(Outcome::saleor_error(
+                            "VariantListing",
+                            product.product.id,
+                            &product.product.name,
+                            &e,
+                        )),
+                    }
+                }
+            }
+
+            //5.
+            //Upload media for products. https://docs.saleor.io/docs/3.x/api-reference/products/inputs/product-media-create-input#
+            // Re-uploading media to an existing product would only duplicate the pictures
+            let has_media =
+                existing.is_some_and(|e| e.media.as_ref().is_some_and(|m| !m.is_empty()));
+            let mut media_ids: Vec<cynic::Id> = self
+                .state
+                .lock()
+                .ids(&key, Step::Media)
+                .cloned()
+                .unwrap_or_default();
+            let media_done = self.state.lock().is_done(&key, Step::Media);
+            if !has_media && !media_done {
+                // A picture an earlier family member already uploaded only gets assigned again
+                let mut images = vec![];
+                for image in &product.images {
+                    let picture = OldKey::Media(head_id, image.clone());
+                    match self.state.lock().id(&picture, Step::Create) {
+                        Some(id) => media_ids.push(id),
+                        None => images.push(image.clone()),
+                    }
+                }
+                match product_media_create(&product_saleor_id, product, &images, self.saleor).await
+                {
+                    Ok(media) => {
+                        for (image, id) in media.created {
+                            let picture = OldKey::Media(head_id, image);
+                            self.state
+                                .lock()
+                                .record(picture, Step::Create, vec![id.clone()])?;
+                            media_ids.push(id);
+                        }
+                        for (image, failure) in &media.failed {
+                            let name = format!("{} ({})", product.product.name, image);
+                            self.report.record(match failure {
+                                MediaFailure::Read(message) => Outcome::error(
+                                    "MediaCreate",
+                                    product.product.id,
+                                    &name,
+                                    message.clone(),
+                                ),
+                                MediaFailure::Saleor(e) => Outcome::saleor_error(
+                                    "MediaCreate",
+                                    product.product.id,
+                                    &name,
+                                    e,
+                                ),
+                            });
+                        }
+                        // The ones that went up are in the state, --resume only sends the rest
+                        if media.failed.is_empty() {
+                            self.state.lock().record(
+                                key.clone(),
+                                Step::Media,
+                                media_ids.clone(),
+                            )?;
+                            self.report.record(Outcome::ok(
+                                "MediaCreate",
+                                product.product.id,
+                                &product.product.name,
+                            ));
+                        }
+                    }
+                    Err(e) => self.report.record(Outcome::saleor_error(
+                        "MediaCreate",
+                        product.product.id,
+                        &product.product.name,
+                        &e,
+                    )),
+                }
+            }
+
+            //6.
+            // Assing media to the variant we created, once all its pictures are up so a resume
+            // doesn't leave the late ones unassigned
+            if let Some(variant_id) = variant_id {
+                let assign = {
+                    let state = self.state.lock();
+                    state.is_done(&key, Step::Media) && !state.is_done(&key, Step::MediaAssign)
+                };
+                if has_media || !assign {
+                    continue;
+                }
+                println!("variant_id:{:?}, media_ids: {:?}", &variant_id, &media_ids);
+                let mut all_assigned = true;
+                for media_id in media_ids {
+                    if let Err(e) = variant_media_assign(&variant_id, &media_id, self.saleor).await
+                    {
+                        self.report.record(Outcome::saleor_error(
+                            "MediaAssign",
+                            product.product.id,
+                            &product.product.name,
+                            &e,
+                        ));
+                        all_assigned = false;
+                    }
+                }
+                if all_assigned {
+                    self.state.lock().record(key, Step::MediaAssign, vec![])?;
+                    self.report.record(Outcome::ok(
+                        "MediaAssign",
+                        product.product.id,
+                        &product.product.name,
+                    ));
+                }
+            }
+        }
+        product.siblings = siblings;
+
+        //7.
+        // Datasheets, manuals and such, the whole family's go to the product
+        let attachments_done = self.state.lock().is_done(&key, Step::Attachments);
+        if !attachments_done {
+            self.upload_attachments(product, &product_saleor_id).await?;
+        }
+
+        //8.
+        // Texts in the other languages
+        let translations_done = self.state.lock().is_done(&key, Step::Translations);
+        if !translations_done && !product.translations.is_empty() {
+            let mut all_translated = true;
+            for translation in &product.translations {
+                if let Err(e) =
+                    product_translate(&product_saleor_id, translation, self.saleor).await
+                {
+                    self.report.record(Outcome::saleor_error(
+                        "ProductTranslate",
+                        product.product.id,
+                        &product.product.name,
+                        &e,
+                    ));
+                    all_translated = false;
+                }
+            }
+            if all_translated {
+                self.state.lock().record(key, Step::Translations, vec![])?;
+                self.report.record(Outcome::ok(
+                    "ProductTranslate",
+                    product.product.id,
+                    &product.product.name,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads every attachment with fileUpload and lists them as `[{"name", "url"}]` in the
+    /// product's `attachments` metadata, storefronts link them from there
+    async fn upload_attachments(
+        &self,
+        product: &FinalProduct,
+        product_saleor_id: &cynic::Id,
+    ) -> anyhow::Result<()> {
+        let mut attachments: Vec<&(String, String)> = vec![];
+        for attachment in std::iter::once(product)
+            .chain(product.siblings.iter())
+            .flat_map(|p| &p.attachments)
+        {
+            if !attachments.iter().any(|a| a.0 == attachment.0) {
+                attachments.push(attachment);
+            }
+        }
+        if attachments.is_empty() {
+            return Ok(());
+        }
+        let mut uploaded = vec![];
+        for (file_name, mime) in attachments.iter().copied() {
+            let file_path = config().media_root.join(file_name);
+            let file = match FileUpload::read_as("variables.file", &file_path, mime) {
+                Ok(file) => file,
+                Err(e) => {
+                    self.report.record(Outcome::error(
+                        "Attachment",
+                        product.product.id,
+                        &product.product.name,
+                        format!("{}: {}", file_name, e),
+                    ));
+                    continue;
+                }
+            };
+            match file_upload(&file, self.saleor).await {
+                Ok(url) => {
+                    self.report.attachment(
+                        product.product.id,
+                        &product.product.name,
+                        file_name,
+                        &url,
+                    );
+                    uploaded.push(serde_json::json!({ "name": file_name, "url": url }));
+                }
+                Err(e) => self.report.record(Outcome::saleor_error(
+                    "Attachment",
+                    product.product.id,
+                    &product.product.name,
+                    &e,
+                )),
+            }
+        }
+        if uploaded.is_empty() {
+            return Ok(());
+        }
+        let value = serde_json::to_string(&uploaded)?;
+        let metadata = vec![MetadataInput {
+            key: "attachments",
+            value: &value,
+        }];
+        match update_metadata(product_saleor_id, metadata, self.saleor).await {
+            Err(e) => self.report.record(Outcome::saleor_error(
+                "Attachments",
+                product.product.id,
+                &product.product.name,
+                &e,
+            )),
+            // The failed ones go again next run, together with the rest
+            Ok(()) if uploaded.len() < attachments.len() => {}
+            Ok(()) => {
+                let key = OldKey::Product(product.product.id);
+                self.state.lock().record(key, Step::Attachments, vec![])?;
+                self.report.record(Outcome::ok(
+                    "Attachments",
+                    product.product.id,
+                    &product.product.name,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use crate::config::config;
+use crate::get_sqls::{FinalCategory, FinalProduct};
+use crate::state::{MigrationState, OldKey};
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+pub const REDIRECTS_CSV: &str = "redirects.csv";
+pub const REDIRECTS_NGINX: &str = "redirects.nginx.conf";
+pub const REDIRECTS_APACHE: &str = "redirects.htaccess";
+
+/// Old shop urls by old id, with the slugs the old shop had before they got de-duplicated
+pub struct OldUrls {
+    categories: HashMap<u32, String>,
+    products: HashMap<u32, String>,
+}
+
+/// A single old url → new storefront path pair
+struct Redirect {
+    /// `category` or `product`
+    kind: &'static str,
+    old_id: u32,
+    from: String,
+    to: String,
+}
+
+impl OldUrls {
+    /// `None` if there's no `redirects` config, then there's no redirect map either. A category an
+    /// earlier run uploaded has its old slug in the state, a product keeps its in `old_slug`
+    pub fn collect(
+        categories: &[Arc<RwLock<FinalCategory>>],
+        products: &[FinalProduct],
+        state: &MigrationState,
+    ) -> Option<Self> {
+        let settings = config().redirects.as_ref()?;
+        let categories = categories
+            .iter()
+            .map(|category| {
+                let category = category.read();
+                let category = category.category.read();
+                let slug = state
+                    .slugs(&OldKey::Category(category.id))
+                    .map_or(category.slug.as_str(), |slugs| slugs.old.as_str());
+                let url = fill(&settings.old_category_url, category.id, slug);
+                (category.id, url)
+            })
+            .collect();
+        let products = products
+            .iter()
+            .flat_map(|p| std::iter::once(p).chain(&p.siblings))
+            .map(|product| {
+                let url = fill(
+                    &settings.old_product_url,
+                    product.product.id,
+                    &product.old_slug,
+                );
+                (product.product.id, url)
+            })
+            .collect();
+        Some(OldUrls {
+            categories,
+            products,
+        })
+    }
+
+    /// Writes the map as csv, nginx `map` and apache rewrite rules. Only what made it into saleor
+    /// gets a redirect, variants lead to the product they became part of
+    pub fn write(
+        &self,
+        categories: &[Arc<RwLock<FinalCategory>>],
+        products: &[FinalProduct],
+    ) -> anyhow::Result<()> {
+        let Some(settings) = &config().redirects else {
+            return Ok(());
+        };
+        let mut redirects = vec![];
+        for category in categories {
+            let category = category.read();
+            if category.saleor_id.is_none() {
+                continue;
+            }
+            let category = category.category.read();
+            if let Some(from) = self.categories.get(&category.id) {
+                redirects.push(Redirect {
+                    kind: "category",
+                    old_id: category.id,
+                    from: from.clone(),
+                    to: fill(&settings.new_category_url, category.id, &category.slug),
+                });
+            }
+        }
+        for product in products {
+            if product.saleor_id.is_none() {
+                continue;
+            }
+            let to = fill(&settings.new_product_url, product.product.id, &product.slug);
+            for old in std::iter::once(product).chain(&product.siblings) {
+                if let Some(from) = self.products.get(&old.product.id) {
+                    redirects.push(Redirect {
+                        kind: "product",
+                        old_id: old.product.id,
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
+                }
+            }
+        }
+
+        deduplicate(&mut redirects);
+        std::fs::write(REDIRECTS_CSV, csv(&redirects))?;
+        std::fs::write(REDIRECTS_NGINX, nginx(&redirects)?)?;
+        std::fs::write(REDIRECTS_APACHE, apache(&redirects)?)?;
+        println!(
+            "Wrote {} redirects to {}, {} and {}",
+            redirects.len(),
+            REDIRECTS_CSV,
+            REDIRECTS_NGINX,
+            REDIRECTS_APACHE
+        );
+        Ok(())
+    }
+}
+
+/// An old url can only lead to one place, the first one wins. Urls that stay the same are dropped
+fn deduplicate(redirects: &mut Vec<Redirect>) {
+    let mut seen = HashSet::new();
+    redirects.retain(|r| {
+        if r.from == r.to {
+            return false;
+        }
+        if !seen.insert(r.from.clone()) {
+            println!(
+                "{} {}: old url {} is taken by another redirect, skipping it",
+                r.kind, r.old_id, r.from
+            );
+            return false;
+        }
+        true
+    });
+}
+
+/// `{id}` and `{slug}` in a url pattern from the config
+fn fill(pattern: &str, id: u32, slug: &str) -> String {
+    pattern
+        .replace("{id}", &id.to_string())
+        .replace("{slug}", slug)
+}
+
+fn csv(redirects: &[Redirect]) -> String {
+    let field = |text: &str| match text.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_owned(),
+    };
+    let mut csv = String::from("type,old_id,from,to\n");
+    for r in redirects {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            r.kind,
+            r.old_id,
+            field(&r.from),
+            field(&r.to)
+        ));
+    }
+    csv
+}
+
+/// The url as a browser sends it, `$request_uri` and apache's query string aren't decoded
+fn encoded(url: &str) -> String {
+    let mut encoded = String::new();
+    for byte in url.bytes() {
+        match byte {
+            b' ' | b'"' | b'<' | b'>' | b'`' | 0..=0x1f | 0x7f..=0xff => {
+                encoded.push_str(&format!("%{:02X}", byte))
+            }
+            _ => encoded.push(byte as char),
+        }
+    }
+    encoded
+}
+
+/// Include in the `http` block and add `if ($new_uri) { return 301 $new_uri; }` to the server
+fn nginx(redirects: &[Redirect]) -> Result<String, std::fmt::Error> {
+    let quote = |text: &str| format!("\"{}\"", encoded(text).replace('\\', "\\\\"));
+    let mut conf = String::new();
+    writeln!(conf, "map $request_uri $new_uri {{")?;
+    writeln!(conf, "    default \"\";")?;
+    for r in redirects {
+        writeln!(conf, "    {} {};", quote(&r.from), quote(&r.to))?;
+    }
+    writeln!(conf, "}}")?;
+    Ok(conf)
+}
+
+/// RewriteRule doesn't see the query string, old urls with one get a RewriteCond for it. The path
+/// RewriteRule sees is decoded, the query string isn't
+fn apache(redirects: &[Redirect]) -> Result<String, std::fmt::Error> {
+    let pattern = |text: &str| regex::escape(text).replace(' ', "\\ ");
+    let mut conf = String::from("RewriteEngine On\n");
+    for r in redirects {
+        let (path, query) = match r.from.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (r.from.as_str(), None),
+        };
+        if let Some(query) = query {
+            writeln!(
+                conf,
+                "RewriteCond %{{QUERY_STRING}} ^{}$",
+                pattern(&encoded(query))
+            )?;
+        }
+        // The trailing ? drops the old query string, NE keeps the %-escapes as they are and an
+        // unescaped % would be a backreference
+        writeln!(
+            conf,
+            "RewriteRule ^/?{}$ {}? [R=301,NE,L]",
+            pattern(path.trim_start_matches('/')),
+            encoded(&r.to).replace('%', "\\%")
+        )?;
+    }
+    Ok(conf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect(kind: &'static str, old_id: u32, from: &str, to: &str) -> Redirect {
+        Redirect {
+            kind,
+            old_id,
+            from: from.to_owned(),
+            to: to.to_owned(),
+        }
+    }
+
+    /// A query string, spaces and quotes, one old url twice and one that doesn't change
+    fn redirects() -> Vec<Redirect> {
+        let mut redirects = vec![
+            redirect(
+                "category",
+                1,
+                r#"/kat.php?id=1&q="a b""#,
+                "/category/tricka/",
+            ),
+            redirect("product", 2, "/p/same/", "/p/same/"),
+            redirect("product", 3, r#"/old dir/it's "q".html"#, "/product/a b/"),
+            redirect("product", 4, r#"/kat.php?id=1&q="a b""#, "/product/other/"),
+        ];
+        deduplicate(&mut redirects);
+        redirects
+    }
+
+    #[test]
+    fn fill_replaces_every_placeholder() {
+        assert_eq!(
+            fill("/kategoria/{id}-{slug}?id={id}", 7, "a b"),
+            "/kategoria/7-a b?id=7"
+        );
+        assert_eq!(fill("/static", 7, "x"), "/static");
+    }
+
+    #[test]
+    fn first_redirect_of_an_old_url_wins() {
+        let redirects = redirects();
+        let ids: Vec<u32> = redirects.iter().map(|r| r.old_id).collect();
+        assert_eq!(ids, [1, 3]);
+        assert_eq!(redirects[0].to, "/category/tricka/");
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        assert_eq!(
+            csv(&redirects()),
+            concat!(
+                "type,old_id,from,to\n",
+                "category,1,\"/kat.php?id=1&q=\"\"a b\"\"\",/category/tricka/\n",
+                "product,3,\"/old dir/it's \"\"q\"\".html\",/product/a b/\n",
+            )
+        );
+    }
+
+    #[test]
+    fn nginx_matches_the_encoded_request() {
+        assert_eq!(
+            nginx(&redirects()).unwrap(),
+            concat!(
+                "map $request_uri $new_uri {\n",
+                "    default \"\";\n",
+                "    \"/kat.php?id=1&q=%22a%20b%22\" \"/category/tricka/\";\n",
+                "    \"/old%20dir/it's%20%22q%22.html\" \"/product/a%20b/\";\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn apache_escapes_patterns_and_targets() {
+        assert_eq!(
+            apache(&redirects()).unwrap(),
+            concat!(
+                "RewriteEngine On\n",
+                "RewriteCond %{QUERY_STRING} ^id=1\\&q=%22a%20b%22$\n",
+                "RewriteRule ^/?kat\\.php$ /category/tricka/? [R=301,NE,L]\n",
+                "RewriteRule ^/?old\\ dir/it's\\ \"q\"\\.html$ /product/a\\%20b/? [R=301,NE,L]\n",
+            )
+        );
+    }
+
+    #[test]
+    fn non_ascii_is_percent_encoded() {
+        assert_eq!(encoded("/tričko?a=b"), "/tri%C4%8Dko?a=b");
+    }
+}
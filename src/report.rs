@@ -0,0 +1,424 @@
+use crate::saleor::SaleorError;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+pub const REPORT_JSON: &str = "migration_report.json";
+pub const REPORT_MD: &str = "migration_report.md";
+
+/// How a single step of a single old row went
+#[derive(Serialize, Debug)]
+pub struct Outcome {
+    pub step: &'static str,
+    pub old_id: String,
+    pub name: String,
+    pub ok: bool,
+    /// Saleor error code, `{:?}` of the mutation's error code enum
+    pub code: Option<String>,
+    pub field: Option<String>,
+    pub message: Option<String>,
+    /// Extra attempts after a taken slug
+    pub retries: u32,
+}
+
+impl Outcome {
+    pub fn ok(step: &'static str, old_id: impl ToString, name: &str) -> Self {
+        Outcome {
+            step,
+            old_id: old_id.to_string(),
+            name: name.to_owned(),
+            ok: true,
+            code: None,
+            field: None,
+            message: None,
+            retries: 0,
+        }
+    }
+
+    /// Failure that isn't a saleor error, e.g. a response without data
+    pub fn error(step: &'static str, old_id: impl ToString, name: &str, message: String) -> Self {
+        Outcome {
+            ok: false,
+            message: Some(message),
+            ..Self::ok(step, old_id, name)
+        }
+    }
+
+    /// Failure with the code, field and message saleor answered with
+    pub fn saleor_error<C: std::fmt::Debug>(
+        step: &'static str,
+        old_id: impl ToString,
+        name: &str,
+        error: &SaleorError<C>,
+    ) -> Self {
+        Outcome {
+            ok: false,
+            code: Some(error.code()),
+            field: error.field(),
+            message: Some(error.to_string()),
+            ..Self::ok(step, old_id, name)
+        }
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+}
+
+/// Old row that never made it to saleor
+#[derive(Serialize, Debug)]
+pub struct Skipped {
+    pub old_id: String,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Slug or SKU that had to change to be unique
+#[derive(Serialize, Debug)]
+pub struct Renamed {
+    /// `category slug`, `product slug` or `sku`
+    pub what: &'static str,
+    pub old_id: String,
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Non-picture file of an old product that went up with fileUpload
+#[derive(Serialize, Debug)]
+pub struct Attachment {
+    pub old_id: String,
+    pub name: String,
+    pub file: String,
+    pub url: String,
+}
+
+/// Old address that didn't make it onto its customer
+#[derive(Serialize, Debug)]
+pub struct InvalidAddress {
+    /// Old user id
+    pub old_id: String,
+    pub email: String,
+    pub address_id: String,
+    pub reason: String,
+}
+
+/// Order line whose product didn't make it to saleor, the order went in without it
+#[derive(Serialize, Debug)]
+pub struct MissingLine {
+    /// Old order id
+    pub old_id: String,
+    pub number: String,
+    pub product_id: Option<u32>,
+    pub name: String,
+}
+
+#[derive(Serialize, Default, Debug)]
+struct ReportData {
+    outcomes: Vec<Outcome>,
+    skipped: Vec<Skipped>,
+    renamed: Vec<Renamed>,
+    attachments: Vec<Attachment>,
+    invalid_addresses: Vec<InvalidAddress>,
+    missing_lines: Vec<MissingLine>,
+}
+
+/// Everything that happened during a run, written as json and markdown once it's over
+#[derive(Default)]
+pub struct Report {
+    data: Mutex<ReportData>,
+}
+
+impl Report {
+    pub fn record(&self, outcome: Outcome) {
+        if !outcome.ok {
+            println!(
+                "{} '{}: {}' failed, code: {}, {}",
+                outcome.step,
+                outcome.old_id,
+                outcome.name,
+                outcome.code.as_deref().unwrap_or("-"),
+                outcome.message.as_deref().unwrap_or("")
+            );
+        }
+        self.data.lock().outcomes.push(outcome);
+    }
+
+    pub fn skipped(&self, old_id: impl ToString, name: &str, reason: &str) {
+        self.data.lock().skipped.push(Skipped {
+            old_id: old_id.to_string(),
+            name: name.to_owned(),
+            reason: reason.to_owned(),
+        });
+    }
+
+    pub fn renamed(
+        &self,
+        what: &'static str,
+        old_id: impl ToString,
+        name: &str,
+        from: &str,
+        to: &str,
+    ) {
+        self.data.lock().renamed.push(Renamed {
+            what,
+            old_id: old_id.to_string(),
+            name: name.to_owned(),
+            from: from.to_owned(),
+            to: to.to_owned(),
+        });
+    }
+
+    pub fn attachment(&self, old_id: impl ToString, name: &str, file: &str, url: &str) {
+        self.data.lock().attachments.push(Attachment {
+            old_id: old_id.to_string(),
+            name: name.to_owned(),
+            file: file.to_owned(),
+            url: url.to_owned(),
+        });
+    }
+
+    pub fn invalid_address(
+        &self,
+        old_id: impl ToString,
+        email: &str,
+        address_id: impl ToString,
+        reason: &str,
+    ) {
+        println!(
+            "address {} of customer '{}: {}' left out: {}",
+            address_id.to_string(),
+            old_id.to_string(),
+            email,
+            reason
+        );
+        self.data.lock().invalid_addresses.push(InvalidAddress {
+            old_id: old_id.to_string(),
+            email: email.to_owned(),
+            address_id: address_id.to_string(),
+            reason: reason.to_owned(),
+        });
+    }
+
+    pub fn missing_line(
+        &self,
+        old_id: impl ToString,
+        number: &str,
+        product_id: Option<u32>,
+        name: &str,
+    ) {
+        println!(
+            "line '{}' of order '{}: {}' left out, product {:?} isn't in saleor",
+            name,
+            old_id.to_string(),
+            number,
+            product_id
+        );
+        self.data.lock().missing_lines.push(MissingLine {
+            old_id: old_id.to_string(),
+            number: number.to_owned(),
+            product_id,
+            name: name.to_owned(),
+        });
+    }
+
+    pub fn write(&self, json_path: &str, md_path: &str) -> anyhow::Result<()> {
+        let data = self.data.lock();
+        std::fs::write(json_path, serde_json::to_string_pretty(&*data)?)?;
+        std::fs::write(md_path, Self::markdown(&data)?)?;
+        println!("Wrote report to {} and {}", json_path, md_path);
+        Ok(())
+    }
+
+    fn markdown(data: &ReportData) -> Result<String, std::fmt::Error> {
+        // (ok, failed) per step and failures per (step, code), sorted so runs can be diffed
+        let mut per_step: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        let mut per_code: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for outcome in &data.outcomes {
+            let counts = per_step.entry(outcome.step).or_default();
+            match outcome.ok {
+                true => counts.0 += 1,
+                false => {
+                    counts.1 += 1;
+                    let code = outcome.code.as_deref().unwrap_or("-");
+                    *per_code.entry((outcome.step, code)).or_default() += 1;
+                }
+            }
+        }
+
+        let mut md = String::new();
+        writeln!(md, "# Migration report\n")?;
+        writeln!(md, "| Step | Ok | Failed |\n|---|---|---|")?;
+        for (step, (ok, failed)) in &per_step {
+            writeln!(md, "| {} | {} | {} |", step, ok, failed)?;
+        }
+
+        writeln!(md, "\n## Errors by code\n")?;
+        writeln!(md, "| Step | Code | Count |\n|---|---|---|")?;
+        for ((step, code), count) in &per_code {
+            writeln!(md, "| {} | {} | {} |", step, code, count)?;
+        }
+
+        writeln!(md, "\n## Failures\n")?;
+        writeln!(
+            md,
+            "| Step | Old id | Name | Code | Field | Message | Retries |\n|---|---|---|---|---|---|---|"
+        )?;
+        for outcome in data.outcomes.iter().filter(|o| !o.ok) {
+            writeln!(
+                md,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                outcome.step,
+                outcome.old_id,
+                escape(&outcome.name),
+                outcome.code.as_deref().unwrap_or(""),
+                outcome.field.as_deref().unwrap_or(""),
+                escape(outcome.message.as_deref().unwrap_or("")),
+                outcome.retries
+            )?;
+        }
+
+        writeln!(md, "\n## Skipped ({})\n", data.skipped.len())?;
+        writeln!(md, "| Old id | Name | Reason |\n|---|---|---|")?;
+        for skipped in &data.skipped {
+            writeln!(
+                md,
+                "| {} | {} | {} |",
+                skipped.old_id,
+                escape(&skipped.name),
+                skipped.reason
+            )?;
+        }
+
+        writeln!(md, "\n## Renamed ({})\n", data.renamed.len())?;
+        writeln!(
+            md,
+            "| What | Old id | Name | From | To |\n|---|---|---|---|---|"
+        )?;
+        for renamed in &data.renamed {
+            writeln!(
+                md,
+                "| {} | {} | {} | {} | {} |",
+                renamed.what,
+                renamed.old_id,
+                escape(&renamed.name),
+                escape(&renamed.from),
+                escape(&renamed.to)
+            )?;
+        }
+
+        writeln!(md, "\n## Attachments ({})\n", data.attachments.len())?;
+        writeln!(md, "| Old id | Name | File | Url |\n|---|---|---|---|")?;
+        for attachment in &data.attachments {
+            writeln!(
+                md,
+                "| {} | {} | {} | {} |",
+                attachment.old_id,
+                escape(&attachment.name),
+                escape(&attachment.file),
+                attachment.url
+            )?;
+        }
+
+        writeln!(
+            md,
+            "\n## Invalid addresses ({})\n",
+            data.invalid_addresses.len()
+        )?;
+        writeln!(
+            md,
+            "| Customer | Email | Address | Reason |\n|---|---|---|---|"
+        )?;
+        for address in &data.invalid_addresses {
+            writeln!(
+                md,
+                "| {} | {} | {} | {} |",
+                address.old_id,
+                escape(&address.email),
+                address.address_id,
+                escape(&address.reason)
+            )?;
+        }
+
+        writeln!(
+            md,
+            "\n## Order lines without a product ({})\n",
+            data.missing_lines.len()
+        )?;
+        writeln!(md, "| Order | Number | Product | Name |\n|---|---|---|---|")?;
+        for line in &data.missing_lines {
+            writeln!(
+                md,
+                "| {} | {} | {} | {} |",
+                line.old_id,
+                escape(&line.number),
+                line.product_id.map(|id| id.to_string()).unwrap_or_default(),
+                escape(&line.name)
+            )?;
+        }
+        Ok(md)
+    }
+}
+
+/// Keeps names with pipes or newlines from breaking the tables
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_counts_and_escapes() {
+        let report = Report::default();
+        let failed = |old_id, code: &str| Outcome {
+            code: Some(code.to_owned()),
+            field: Some("slug".to_owned()),
+            ..Outcome::error(
+                "product",
+                old_id,
+                "Tričko | modré",
+                "taken\nagain".to_owned(),
+            )
+        };
+        report.record(Outcome::ok("product", 1, "Mikina"));
+        report.record(failed(2, "UNIQUE").retries(3));
+        report.record(failed(3, "UNIQUE"));
+        report.record(failed(4, "INVALID"));
+        report.record(Outcome::ok("category", 5, "Oblečenie"));
+        report.skipped(6, "Bez ceny", "no price");
+        report.renamed("sku", 7, "Čiapka", "CAP", "CAP-1");
+
+        let md = Report::markdown(&report.data.lock()).unwrap();
+        let lines: Vec<&str> = md.lines().collect();
+        let after = |heading: &str, skip: usize| {
+            let start = lines.iter().position(|line| *line == heading).unwrap();
+            lines[start + skip..]
+                .iter()
+                .take_while(|line| !line.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            after("# Migration report", 4),
+            ["| category | 1 | 0 |", "| product | 1 | 3 |"]
+        );
+        assert_eq!(
+            after("## Errors by code", 4),
+            ["| product | INVALID | 1 |", "| product | UNIQUE | 2 |"]
+        );
+        assert_eq!(
+            after("## Failures", 4)[0],
+            "| product | 2 | Tričko \\| modré | UNIQUE | slug | taken again | 3 |"
+        );
+        assert_eq!(after("## Skipped (1)", 4), ["| 6 | Bez ceny | no price |"]);
+        assert_eq!(
+            after("## Renamed (1)", 4),
+            ["| sku | 7 | Čiapka | CAP | CAP-1 |"]
+        );
+        assert!(md.contains("## Attachments (0)"));
+    }
+}
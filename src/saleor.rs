@@ -0,0 +1,533 @@
+use crate::config::config;
+use crate::gql_queries::{CreateToken, CreateTokenVariables, RefreshToken, RefreshTokenVariables};
+use crate::media_server;
+use async_native_tls::TlsConnector;
+use base64::Engine;
+use cynic::{GraphQlResponse, MutationBuilder, Operation};
+use parking_lot::RwLock;
+use reqwest::multipart::{Form, Part};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use surf::middleware::{Middleware, Next};
+use surf::{Client, Request, Response};
+
+const MAX_ATTEMPTS: u32 = 5;
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+/// Refresh this long before the jwt expires, so no request goes out with a dying one
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+/// Saleor's default access token lifetime, for when the jwt's `exp` can't be read
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+/// One error from a mutation's `errors` list
+#[derive(Clone, Debug)]
+pub struct MutationError<C> {
+    pub field: Option<String>,
+    pub message: Option<String>,
+    pub code: C,
+}
+
+/// Mutation responses, so `Saleor::mutate` can find their `errors`
+pub trait MutationErrors {
+    type Code: Debug;
+    fn mutation_errors(&self) -> Vec<MutationError<Self::Code>>;
+}
+
+/// What went wrong talking to saleor. `C` is the error code enum of the mutation
+#[derive(Debug)]
+pub enum SaleorError<C = ()> {
+    /// No usable response, even after retrying
+    Http(String),
+    /// Couldn't refresh the jwt nor log in again
+    Auth(String),
+    /// Top level graphql errors, like a broken query or a missing permission
+    Graphql(Vec<String>),
+    /// The mutation's own `errors`
+    Mutation(Vec<MutationError<C>>),
+    /// Response without any data
+    NoData,
+}
+
+impl<C: Debug> SaleorError<C> {
+    /// Code for the report, the mutation's own one if saleor sent one
+    pub fn code(&self) -> String {
+        match self {
+            SaleorError::Http(_) => "Http".to_owned(),
+            SaleorError::Auth(_) => "Auth".to_owned(),
+            SaleorError::Graphql(_) => "GraphqlError".to_owned(),
+            SaleorError::Mutation(errors) => errors
+                .first()
+                .map(|e| format!("{:?}", e.code))
+                .unwrap_or_default(),
+            SaleorError::NoData => "NoData".to_owned(),
+        }
+    }
+
+    pub fn field(&self) -> Option<String> {
+        match self {
+            SaleorError::Mutation(errors) => errors.iter().find_map(|e| e.field.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn has_code(&self, code: C) -> bool
+    where
+        C: PartialEq,
+    {
+        matches!(self, SaleorError::Mutation(errors) if errors.iter().any(|e| e.code == code))
+    }
+}
+
+impl<C: Debug> std::fmt::Display for SaleorError<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaleorError::Http(message) | SaleorError::Auth(message) => write!(f, "{}", message),
+            SaleorError::Graphql(messages) => write!(f, "{}", messages.join("; ")),
+            SaleorError::Mutation(errors) => {
+                let messages: Vec<String> = errors
+                    .iter()
+                    .map(|e| {
+                        format!(
+                            "{:?} {}: {}",
+                            e.code,
+                            e.field.as_deref().unwrap_or("-"),
+                            e.message.as_deref().unwrap_or("")
+                        )
+                    })
+                    .collect();
+                write!(f, "{}", messages.join("; "))
+            }
+            SaleorError::NoData => write!(f, "response without data"),
+        }
+    }
+}
+
+impl<C: Debug> std::error::Error for SaleorError<C> {}
+
+/// Why a request didn't give a usable response
+enum PostError {
+    /// Saleor couldn't be reached or answered 5xx/429, it didn't do anything with the request
+    Transient(String),
+    /// Anything else, a mutation sent again could end up done twice
+    Fatal(String),
+}
+
+impl From<PostError> for String {
+    fn from(error: PostError) -> Self {
+        match error {
+            PostError::Transient(message) | PostError::Fatal(message) => message,
+        }
+    }
+}
+
+/// File sent along with a mutation as a graphql multipart request
+pub struct FileUpload {
+    /// Where the `Upload` goes in the operation, e.g. `variables.input.backgroundImage`
+    pub path: &'static str,
+    pub file_name: String,
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+impl FileUpload {
+    /// Reads `file_name` from `media_dir()`, the mime type comes from its extension
+    pub fn read(path: &'static str, file_name: &str) -> std::io::Result<Self> {
+        let file_path = config().media_dir().join(file_name);
+        Self::read_as(path, &file_path, media_server::mime(&file_path))
+    }
+
+    pub fn read_as(path: &'static str, file_path: &Path, mime: &str) -> std::io::Result<Self> {
+        Ok(FileUpload {
+            path,
+            file_name: file_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            mime: mime.to_owned(),
+            bytes: std::fs::read(file_path)?,
+        })
+    }
+}
+
+struct Token {
+    /// `Bearer ...`, ready for the Authorization header
+    jwt: String,
+    refresh_token: Option<String>,
+    expires_at: Instant,
+}
+
+impl Token {
+    fn new(token: String, refresh_token: Option<String>) -> Self {
+        #[derive(Deserialize)]
+        struct Claims {
+            exp: i64,
+        }
+        let lifetime = token
+            .split('.')
+            .nth(1)
+            .and_then(|payload| {
+                base64::engine::general_purpose::URL_SAFE_NO_PAD
+                    .decode(payload)
+                    .ok()
+            })
+            .and_then(|payload| serde_json::from_slice::<Claims>(&payload).ok())
+            .and_then(|claims| u64::try_from(claims.exp - chrono::Utc::now().timestamp()).ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        Token {
+            jwt: format!("Bearer {}", token),
+            refresh_token,
+            expires_at: Instant::now() + lifetime,
+        }
+    }
+}
+
+/// Owns the client and the jwt, every graphql request to saleor goes through here
+pub struct Saleor {
+    client: Client,
+    /// For multipart requests, surf can't build those
+    upload_client: reqwest::Client,
+    token: RwLock<Token>,
+    /// Only one worker refreshes the jwt, the rest wait for its result
+    refreshing: tokio::sync::Mutex<()>,
+}
+
+impl Saleor {
+    pub async fn login() -> anyhow::Result<Self> {
+        let tls_connector = Some(Arc::new(
+            TlsConnector::new()
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true),
+        ));
+        let surf_config = surf::Config::new().set_tls_config(tls_connector);
+        let client: Client = surf_config.try_into()?;
+        let upload_client = reqwest::Client::builder()
+            .tls_built_in_root_certs(false)
+            .danger_accept_invalid_certs(true)
+            // IF PROBLEMS WITH LOCALHOST/ SELF SIGNED CERTS!
+            // .add_root_certificate(cert)
+            .build()?;
+        let saleor = Saleor {
+            client: client.with(Throttle),
+            upload_client,
+            // already expired, so the first jwt() logs in
+            token: RwLock::new(Token {
+                jwt: String::new(),
+                refresh_token: None,
+                expires_at: Instant::now(),
+            }),
+            refreshing: tokio::sync::Mutex::new(()),
+        };
+        println!("Logging into saleor...");
+        saleor.jwt().await.map_err(anyhow::Error::msg)?;
+        println!("Success!");
+        Ok(saleor)
+    }
+
+    /// Runs a query, graphql errors and missing data are errors
+    pub async fn query<Q, V>(&self, operation: Operation<Q, V>) -> Result<Q, SaleorError>
+    where
+        Q: DeserializeOwned + 'static,
+        V: Serialize + Debug,
+    {
+        self.run(&operation, None).await
+    }
+
+    /// Runs a mutation, the mutation's own `errors` are errors too
+    pub async fn mutate<Q, V>(&self, operation: Operation<Q, V>) -> Result<Q, SaleorError<Q::Code>>
+    where
+        Q: MutationErrors + DeserializeOwned + 'static,
+        V: Serialize + Debug,
+    {
+        let data = self.run::<Q, V, Q::Code>(&operation, None).await?;
+        Self::check(data)
+    }
+
+    /// Same as `mutate`, with the file uploaded as a graphql multipart request
+    pub async fn mutate_with_file<Q, V>(
+        &self,
+        operation: Operation<Q, V>,
+        file: &FileUpload,
+    ) -> Result<Q, SaleorError<Q::Code>>
+    where
+        Q: MutationErrors + DeserializeOwned + 'static,
+        V: Serialize + Debug,
+    {
+        let data = self.run::<Q, V, Q::Code>(&operation, Some(file)).await?;
+        Self::check(data)
+    }
+
+    fn check<Q: MutationErrors>(data: Q) -> Result<Q, SaleorError<Q::Code>> {
+        let errors = data.mutation_errors();
+        // Only the errors, the variables carry customers' names and addresses
+        if !errors.is_empty() {
+            println!("{:?}", &errors);
+            return Err(SaleorError::Mutation(errors));
+        }
+        Ok(data)
+    }
+
+    async fn run<Q, V, C>(
+        &self,
+        operation: &Operation<Q, V>,
+        file: Option<&FileUpload>,
+    ) -> Result<Q, SaleorError<C>>
+    where
+        Q: DeserializeOwned + 'static,
+        V: Serialize + Debug,
+    {
+        let mut refreshed = false;
+        loop {
+            let jwt = self.jwt().await.map_err(SaleorError::Auth)?;
+            let response: GraphQlResponse<Q> = self
+                .post(operation, Some(&jwt), file)
+                .await
+                .map_err(SaleorError::Http)?;
+            let errors = response.errors.unwrap_or_default();
+            // Our clock and saleor's can disagree on when it expires, refresh once and try again
+            if !refreshed && errors.iter().any(|e| e.message == "Signature has expired") {
+                self.expire(&jwt);
+                refreshed = true;
+                continue;
+            }
+            if !errors.is_empty() {
+                println!("{:?}", &operation.query);
+                println!("{:?}", &errors);
+                return Err(SaleorError::Graphql(
+                    errors.into_iter().map(|e| e.message).collect(),
+                ));
+            }
+            return response.data.ok_or(SaleorError::NoData);
+        }
+    }
+
+    /// Sends the operation, retrying with a growing backoff while saleor is unreachable or
+    /// overloaded. Once it answered with a body nothing is retried, mutations aren't idempotent
+    async fn post<Q, V>(
+        &self,
+        operation: &Operation<Q, V>,
+        jwt: Option<&str>,
+        file: Option<&FileUpload>,
+    ) -> Result<GraphQlResponse<Q>, String>
+    where
+        Q: DeserializeOwned,
+        V: Serialize,
+    {
+        let mut backoff = FIRST_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let result = match file {
+                None => self.post_json(operation, jwt).await,
+                Some(file) => self.post_multipart(operation, jwt, file).await,
+            };
+            match result {
+                Ok(response) => return Ok(response),
+                Err(PostError::Transient(e)) if attempt < MAX_ATTEMPTS => {
+                    println!(
+                        "request to saleor failed ({}), retrying in {:?}",
+                        e, backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn post_json<Q, V>(
+        &self,
+        operation: &Operation<Q, V>,
+        jwt: Option<&str>,
+    ) -> Result<GraphQlResponse<Q>, PostError>
+    where
+        Q: DeserializeOwned,
+        V: Serialize,
+    {
+        let mut request = self
+            .client
+            .post(&config().gql_endpoint)
+            .body_json(operation)
+            .map_err(|e| PostError::Fatal(e.to_string()))?;
+        if let Some(jwt) = jwt {
+            request = request.header("Authorization", jwt);
+        }
+        let mut response = request
+            .await
+            .map_err(|e| PostError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status.is_server_error() || status == surf::StatusCode::TooManyRequests {
+            return Err(PostError::Transient(format!("saleor answered {}", status)));
+        }
+        response
+            .body_json()
+            .await
+            .map_err(|e| PostError::Fatal(e.to_string()))
+    }
+
+    async fn post_multipart<Q, V>(
+        &self,
+        operation: &Operation<Q, V>,
+        jwt: Option<&str>,
+        file: &FileUpload,
+    ) -> Result<GraphQlResponse<Q>, PostError>
+    where
+        Q: DeserializeOwned,
+        V: Serialize,
+    {
+        // The surf middleware doesn't see these, so they wait here
+        throttle().await;
+        let file_part = Part::bytes(file.bytes.clone())
+            .file_name(file.file_name.clone())
+            .mime_str(&file.mime)
+            .map_err(|e| PostError::Fatal(e.to_string()))?;
+        let map_part = Part::text(serde_json::json!({ "1": [file.path] }).to_string());
+        let operation =
+            serde_json::to_string(operation).map_err(|e| PostError::Fatal(e.to_string()))?;
+        let gql_part = Part::text(operation);
+        let form = Form::new()
+            .part("operations", gql_part)
+            .part("map", map_part)
+            .part("1", file_part);
+        let mut request = self
+            .upload_client
+            .post(&config().gql_endpoint)
+            .multipart(form);
+        if let Some(jwt) = jwt {
+            request = request.header("Authorization", jwt);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PostError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(PostError::Transient(format!("saleor answered {}", status)));
+        }
+        let text = response
+            .text()
+            .await
+            .map_err(|e| PostError::Fatal(e.to_string()))?;
+        serde_json::from_str(&text).map_err(|e| PostError::Fatal(e.to_string()))
+    }
+
+    /// The `Bearer ...` Authorization header, refreshed first if it's about to expire
+    pub async fn jwt(&self) -> Result<String, String> {
+        if let Some(jwt) = self.valid_jwt() {
+            return Ok(jwt);
+        }
+        let _refreshing = self.refreshing.lock().await;
+        // Whoever held the lock before most likely refreshed it already
+        if let Some(jwt) = self.valid_jwt() {
+            return Ok(jwt);
+        }
+        let refresh_token = self.token.read().refresh_token.clone();
+        let token = match refresh_token {
+            Some(refresh_token) => match self.token_refresh(&refresh_token).await {
+                Ok(token) => token,
+                Err(e) => {
+                    println!("Refreshing the jwt failed ({}), logging in again...", e);
+                    self.token_create().await?
+                }
+            },
+            None => self.token_create().await?,
+        };
+        let jwt = token.jwt.clone();
+        *self.token.write() = token;
+        Ok(jwt)
+    }
+
+    fn valid_jwt(&self) -> Option<String> {
+        let token = self.token.read();
+        (token.expires_at > Instant::now() + REFRESH_MARGIN).then(|| token.jwt.clone())
+    }
+
+    /// Makes the next jwt() refresh, unless someone already replaced this jwt
+    fn expire(&self, jwt: &str) {
+        let mut token = self.token.write();
+        if token.jwt == jwt {
+            token.expires_at = Instant::now();
+        }
+    }
+
+    async fn token_create(&self) -> Result<Token, String> {
+        let login_operation = CreateToken::build(CreateTokenVariables {
+            email: &config().email,
+            password: &config().password,
+        });
+        let response = self.post(&login_operation, None, None).await?;
+        let create = response
+            .data
+            .and_then(|d| d.token_create)
+            .ok_or_else(|| format!("login failed: {:?}", response.errors))?;
+        let token = create
+            .token
+            .ok_or_else(|| format!("login failed: {:?}", create.errors))?;
+        Ok(Token::new(token, create.refresh_token))
+    }
+
+    async fn token_refresh(&self, refresh_token: &str) -> Result<Token, String> {
+        let refresh_operation = RefreshToken::build(RefreshTokenVariables { refresh_token });
+        let response = self.post(&refresh_operation, None, None).await?;
+        let refresh = response
+            .data
+            .and_then(|d| d.token_refresh)
+            .ok_or_else(|| format!("token refresh failed: {:?}", response.errors))?;
+        let token = refresh
+            .token
+            .ok_or_else(|| format!("token refresh failed: {:?}", refresh.errors))?;
+        // Only the jwt is new, the refresh token stays valid
+        Ok(Token::new(token, Some(refresh_token.to_owned())))
+    }
+}
+
+/// Spaces out requests so saleor gets at most `requests_per_second` of them over all workers
+struct RateLimit {
+    interval: Option<Duration>,
+    next: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+static RATE_LIMIT: OnceLock<RateLimit> = OnceLock::new();
+
+impl RateLimit {
+    async fn wait(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let mut next = self.next.lock().await;
+        let now = tokio::time::Instant::now();
+        if *next > now {
+            tokio::time::sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + interval;
+    }
+}
+
+/// Waits for the next free slot of the shared rate limit
+async fn throttle() {
+    RATE_LIMIT
+        .get_or_init(|| RateLimit {
+            interval: config()
+                .requests_per_second
+                // Config::load only takes rates this works for
+                .map(|rps| Duration::from_secs_f64(1.0 / rps)),
+            next: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        })
+        .wait()
+        .await;
+}
+
+/// Surf middleware that puts every request of a client through the shared rate limit
+struct Throttle;
+
+#[surf::utils::async_trait]
+impl Middleware for Throttle {
+    async fn handle(&self, req: Request, client: Client, next: Next<'_>) -> surf::Result<Response> {
+        throttle().await;
+        next.run(req, client).await
+    }
+}
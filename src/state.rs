@@ -0,0 +1,254 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+pub const STATE_FILE: &str = "migration_state.jsonl";
+
+/// Which old db row (or product type name) a state record belongs to
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum OldKey {
+    Category(u32),
+    Product(u32),
+    Customer(u32),
+    Order(u32),
+    /// Discount percentage, every old discount with it ends up in the same sale
+    Sale(String),
+    ProductType(String),
+    /// A picture of an old product family, keyed by the head, so a rerun only uploads the ones
+    /// that didn't make it
+    Media(u32, String),
+}
+
+/// Every round-trip a single old row goes through on its way to Saleor
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Step {
+    Create,
+    /// Attributes assigned to a product type
+    Attributes,
+    ChannelListing,
+    Variant,
+    VariantListing,
+    Media,
+    MediaAssign,
+    Attachments,
+    Translations,
+    Catalogue,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StateRecord {
+    pub key: OldKey,
+    pub step: Step,
+    pub saleor_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slugs: Option<Slugs>,
+}
+
+/// Slug of the old row and the one it went to saleor with, they differ when it had to be made
+/// unique
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Slugs {
+    pub old: String,
+    pub new: String,
+}
+
+/// Append-only log of finished steps, one json object per line, so a crashed run can pick up
+/// where it stopped instead of wiping saleor and starting over
+pub struct MigrationState {
+    file: std::fs::File,
+    done: HashMap<(OldKey, Step), Vec<cynic::Id>>,
+    slugs: HashMap<OldKey, Slugs>,
+}
+
+impl MigrationState {
+    /// Without `resume` a non-empty state file is refused, so a forgotten `--resume` can't
+    /// throw away the progress of the previous run
+    pub fn open(path: &str, resume: bool) -> anyhow::Result<Self> {
+        let mut done = HashMap::new();
+        let mut slugs = HashMap::new();
+        if let Ok(existing) = std::fs::File::open(path) {
+            for (i, line) in BufReader::new(existing).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if !resume {
+                    anyhow::bail!(
+                        "state file '{path}' already has records, run with --resume or delete it"
+                    );
+                }
+                //a half written last line is what a crash looks like, that step just reruns
+                let Ok(record) = serde_json::from_str::<StateRecord>(&line) else {
+                    println!("skipping broken state line {}", i + 1);
+                    continue;
+                };
+                if let Some(record_slugs) = record.slugs {
+                    slugs.insert(record.key.clone(), record_slugs);
+                }
+                done.insert(
+                    (record.key, record.step),
+                    record.saleor_ids.into_iter().map(cynic::Id::new).collect(),
+                );
+            }
+        }
+        let file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("failed opening state file '{path}'"))?;
+        if resume {
+            println!("Resuming with {} finished steps", done.len());
+        }
+        Ok(MigrationState { file, done, slugs })
+    }
+
+    pub fn is_done(&self, key: &OldKey, step: Step) -> bool {
+        self.done.contains_key(&(key.clone(), step))
+    }
+
+    pub fn ids(&self, key: &OldKey, step: Step) -> Option<&Vec<cynic::Id>> {
+        self.done.get(&(key.clone(), step))
+    }
+
+    /// First saleor id a step produced, e.g. the product id for `Step::Create`
+    pub fn id(&self, key: &OldKey, step: Step) -> Option<cynic::Id> {
+        self.ids(key, step).and_then(|ids| ids.first().cloned())
+    }
+
+    /// Slugs a step recorded with `record_slugs`
+    pub fn slugs(&self, key: &OldKey) -> Option<&Slugs> {
+        self.slugs.get(key)
+    }
+
+    pub fn record(
+        &mut self,
+        key: OldKey,
+        step: Step,
+        saleor_ids: Vec<cynic::Id>,
+    ) -> anyhow::Result<()> {
+        self.write(key, step, saleor_ids, None)
+    }
+
+    /// Same as `record`, a rerun reuses the slug instead of making a new one
+    pub fn record_slugs(
+        &mut self,
+        key: OldKey,
+        step: Step,
+        saleor_ids: Vec<cynic::Id>,
+        slugs: Slugs,
+    ) -> anyhow::Result<()> {
+        self.write(key, step, saleor_ids, Some(slugs))
+    }
+
+    fn write(
+        &mut self,
+        key: OldKey,
+        step: Step,
+        saleor_ids: Vec<cynic::Id>,
+        slugs: Option<Slugs>,
+    ) -> anyhow::Result<()> {
+        let record = StateRecord {
+            key: key.clone(),
+            step,
+            saleor_ids: saleor_ids.iter().map(|id| id.inner().to_owned()).collect(),
+            slugs: slugs.clone(),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&record)?)?;
+        self.file.flush()?;
+        if let Some(slugs) = slugs {
+            self.slugs.insert(key.clone(), slugs);
+        }
+        self.done.insert((key, step), saleor_ids);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> String {
+        let file = format!("state-test-{}-{}.jsonl", name, std::process::id());
+        std::env::temp_dir().join(file).display().to_string()
+    }
+
+    #[test]
+    fn records_survive_a_restart() {
+        let path = path("round-trip");
+        let product = OldKey::Product(7);
+        let picture = OldKey::Media(7, "a b/č.jpg".to_owned());
+        let category = OldKey::Category(3);
+        {
+            let mut state = MigrationState::open(&path, false).unwrap();
+            state
+                .record(
+                    product.clone(),
+                    Step::Create,
+                    vec![cynic::Id::new("UHJvZHVjdDox")],
+                )
+                .unwrap();
+            state
+                .record(product.clone(), Step::ChannelListing, vec![])
+                .unwrap();
+            state
+                .record(picture.clone(), Step::Create, vec![cynic::Id::new("media")])
+                .unwrap();
+            let slugs = Slugs {
+                old: "tricka".to_owned(),
+                new: "tricka-x1y2".to_owned(),
+            };
+            state
+                .record_slugs(
+                    category.clone(),
+                    Step::Create,
+                    vec![cynic::Id::new("cat")],
+                    slugs,
+                )
+                .unwrap();
+        }
+        // a crash half way through writing a line
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, "{{\"key\":{{\"Product\":8}},\"st").unwrap();
+        drop(file);
+
+        let state = MigrationState::open(&path, true).unwrap();
+        assert_eq!(
+            state.id(&product, Step::Create),
+            Some(cynic::Id::new("UHJvZHVjdDox"))
+        );
+        assert!(state.is_done(&product, Step::ChannelListing));
+        assert_eq!(state.ids(&product, Step::ChannelListing), Some(&vec![]));
+        assert!(!state.is_done(&product, Step::Variant));
+        assert!(!state.is_done(&OldKey::Product(8), Step::Create));
+        assert_eq!(
+            state.id(&picture, Step::Create),
+            Some(cynic::Id::new("media"))
+        );
+        assert_eq!(
+            state.slugs(&category).map(|slugs| slugs.new.as_str()),
+            Some("tricka-x1y2")
+        );
+        assert_eq!(state.slugs(&product), None);
+        drop(state);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn records_without_resume_are_refused() {
+        let path = path("no-resume");
+        {
+            let mut state = MigrationState::open(&path, false).unwrap();
+            state
+                .record(OldKey::Sale("10".to_owned()), Step::Create, vec![])
+                .unwrap();
+        }
+        assert!(MigrationState::open(&path, false).is_err());
+        assert!(MigrationState::open(&path, true).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}